@@ -0,0 +1,72 @@
+//! Benchmarks, run with `cargo bench`. A benchmark's name can be given to run only it.
+//!
+//! libtest's benchmark harness needs a nightly compiler, so this is a small stand-in for it which
+//! reports the median time per iteration over a number of samples in the same format.
+
+extern crate trip;
+
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use trip::candidate::rand_passwd;
+use trip::rng::Counter;
+
+const SAMPLES: usize = 50;
+
+const SAMPLE_TIME: Duration = Duration::from_millis(20);
+
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+    // Find how many iterations fill a sample, warming up on the way.
+    let mut iters = 1u64;
+
+    loop {
+        let start = Instant::now();
+
+        for _ in 0..iters {
+            f();
+        }
+
+        if start.elapsed() >= SAMPLE_TIME {
+            break;
+        }
+
+        iters *= 2;
+    }
+
+    let mut ns: Vec<f64> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+
+            for _ in 0..iters {
+                f();
+            }
+
+            let elapsed = start.elapsed();
+            (elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64) / iters as f64
+        })
+        .collect();
+
+    ns.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = ns[SAMPLES / 2];
+    let spread = ns[SAMPLES * 3 / 4] - ns[SAMPLES / 4];
+    println!("test {} ... bench: {:>11.0} ns/iter (+/- {:.0})", name, median, spread);
+}
+
+fn main() {
+    let filters: Vec<String> = env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let selected = |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f));
+
+    if selected("bench_trip") {
+        bench("bench_trip", || {
+            black_box(trip::trip(black_box(*b"foofoofo")));
+        });
+    }
+
+    if selected("bench_rand_passwd") {
+        let mut rng = Counter::from_entropy();
+
+        bench("bench_rand_passwd", || {
+            black_box(rand_passwd(&mut rng));
+        });
+    }
+}
@@ -0,0 +1,64 @@
+#ifndef TRIP_H
+#define TRIP_H
+
+/*
+ * A C API for trip's tripcode function. Link with -ltrip.
+ *
+ * Passwords are given as a pointer and a length, and only their first eight
+ * bytes are used, as with crypt(3).
+ */
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Error codes. trip_strerror describes them. */
+#define TRIP_OK 0
+#define TRIP_ERR_NULL (-1)
+#define TRIP_ERR_PROFILE (-2)
+#define TRIP_ERR_SALT (-3)
+#define TRIP_ERR_TRIPCODE (-4)
+
+/* crypt(3) implementations, which handle salt characters outside ./0-9A-Za-z
+ * differently. */
+#define TRIP_COMPAT_FREESEC 0      /* musl and PHP before 8.0 */
+#define TRIP_COMPAT_STRICT 1       /* current glibc, libxcrypt and PHP 8 */
+#define TRIP_COMPAT_UFC 2          /* older glibc */
+#define TRIP_COMPAT_UFC_UNSIGNED 3 /* older glibc on ARM and PowerPC */
+#define TRIP_COMPAT_BSD 4          /* FreeBSD and NetBSD */
+
+/* How a board turns a password into a tripcode. Where a profile is taken, a
+ * null pointer stands for TRIP_PROFILE_2CHANNEL. */
+struct trip_profile {
+	/* Whether salt characters are mapped into ./0-9A-Za-z as 2channel does,
+	 * in which case compat makes no difference. */
+	int map_salt;
+	/* One of the TRIP_COMPAT_ constants. */
+	int compat;
+};
+
+#define TRIP_PROFILE_2CHANNEL { 1, TRIP_COMPAT_FREESEC }
+
+/* The size of a tripcode including its terminating NUL. */
+#define TRIP_TRIPCODE_SIZE 11
+
+/* Writes the tripcode of password, followed by a NUL, to the
+ * TRIP_TRIPCODE_SIZE bytes at out. Returns TRIP_OK or an error code. */
+int trip_compute(const char *password, size_t password_len,
+                 const struct trip_profile *profile, char *out);
+
+/* Returns 1 if password generates expected, a NUL-terminated tripcode, 0 if it
+ * does not, or an error code. */
+int trip_verify(const char *password, size_t password_len,
+                const struct trip_profile *profile, const char *expected);
+
+/* Returns a static, NUL-terminated description of an error code. */
+const char *trip_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,326 @@
+//! A C API for the tripcode function, declared in `include/trip.h`.
+//!
+//! Passwords are given as a pointer and a length, and only their first eight bytes are used, as
+//! with crypt(3). Functions return `TRIP_OK` or one of the negative `TRIP_ERR_` codes, except
+//! `trip_verify`, which returns 1 or 0 when it succeeds.
+
+extern crate trip as core_trip;
+
+use core_trip::trip::{trip_with, Compat, Profile};
+use core_trip::Tripcode;
+use core_trip::zeroize::Zeroizing;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::slice;
+
+// Defines the constants, and lists them so that the tests can check them against the header.
+macro_rules! constants {
+    ($($name:ident: $ty:ty = $value:expr;)*) => {
+        $(pub const $name: $ty = $value;)*
+
+        #[cfg(test)]
+        const CONSTANTS: &[(&str, i64)] = &[$((stringify!($name), $name as i64)),*];
+    };
+}
+
+constants! {
+    TRIP_OK: c_int = 0;
+    TRIP_ERR_NULL: c_int = -1;
+    TRIP_ERR_PROFILE: c_int = -2;
+    TRIP_ERR_SALT: c_int = -3;
+    TRIP_ERR_TRIPCODE: c_int = -4;
+
+    TRIP_COMPAT_FREESEC: c_int = 0;
+    TRIP_COMPAT_STRICT: c_int = 1;
+    TRIP_COMPAT_UFC: c_int = 2;
+    TRIP_COMPAT_UFC_UNSIGNED: c_int = 3;
+    TRIP_COMPAT_BSD: c_int = 4;
+
+    TRIP_TRIPCODE_SIZE: usize = 11;
+}
+
+/// `struct trip_profile`: how a board turns a password into a tripcode.
+#[repr(C)]
+pub struct TripProfile {
+    pub map_salt: c_int,
+    pub compat: c_int,
+}
+
+impl TripProfile {
+    unsafe fn resolve(profile: *const TripProfile) -> Result<Profile, c_int> {
+        let profile = match profile.as_ref() {
+            Some(profile) => profile,
+            None => return Ok(Profile::TWO_CHANNEL),
+        };
+
+        let compat = match profile.compat {
+            TRIP_COMPAT_FREESEC => Compat::FreeSec,
+            TRIP_COMPAT_STRICT => Compat::Strict,
+            TRIP_COMPAT_UFC => Compat::Ufc,
+            TRIP_COMPAT_UFC_UNSIGNED => Compat::UfcUnsigned,
+            TRIP_COMPAT_BSD => Compat::Bsd,
+            _ => return Err(TRIP_ERR_PROFILE),
+        };
+
+        Ok(Profile { map_salt: profile.map_salt != 0, compat })
+    }
+}
+
+unsafe fn tripcode(
+    password: *const c_char,
+    password_len: usize,
+    profile: *const TripProfile,
+) -> Result<Tripcode, c_int> {
+    if password.is_null() && password_len != 0 {
+        return Err(TRIP_ERR_NULL);
+    }
+
+    let profile = TripProfile::resolve(profile)?;
+    let mut passwd = Zeroizing::new([0u8; 8]);
+
+    if password_len != 0 {
+        let password = slice::from_raw_parts(password as *const u8, password_len);
+        let len = password.len().min(8);
+        passwd[..len].copy_from_slice(&password[..len]);
+    }
+
+    trip_with(*passwd, profile).ok_or(TRIP_ERR_SALT)
+}
+
+/// Writes the tripcode of a password, followed by a NUL, to the `TRIP_TRIPCODE_SIZE` bytes at
+/// `out`.
+///
+/// # Safety
+///
+/// `password` must point to `password_len` readable bytes, `profile` must be null or point to a
+/// `trip_profile`, and `out` must point to `TRIP_TRIPCODE_SIZE` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn trip_compute(
+    password: *const c_char,
+    password_len: usize,
+    profile: *const TripProfile,
+    out: *mut c_char,
+) -> c_int {
+    if out.is_null() {
+        return TRIP_ERR_NULL;
+    }
+
+    match tripcode(password, password_len, profile) {
+        Ok(tripcode) => {
+            let out = slice::from_raw_parts_mut(out as *mut u8, TRIP_TRIPCODE_SIZE);
+            let (tripcode_out, nul) = out.split_at_mut(TRIP_TRIPCODE_SIZE - 1);
+            tripcode_out.copy_from_slice(tripcode.as_bytes());
+            nul[0] = 0;
+            TRIP_OK
+        }
+        Err(e) => e,
+    }
+}
+
+/// Returns 1 if a password generates `expected`, a NUL-terminated tripcode, and 0 if not.
+///
+/// # Safety
+///
+/// `password` must point to `password_len` readable bytes, `profile` must be null or point to a
+/// `trip_profile`, and `expected` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn trip_verify(
+    password: *const c_char,
+    password_len: usize,
+    profile: *const TripProfile,
+    expected: *const c_char,
+) -> c_int {
+    if expected.is_null() {
+        return TRIP_ERR_NULL;
+    }
+
+    let expected = match Tripcode::new(CStr::from_ptr(expected).to_bytes()) {
+        Ok(expected) => expected,
+        Err(_) => return TRIP_ERR_TRIPCODE,
+    };
+
+    match tripcode(password, password_len, profile) {
+        Ok(tripcode) => (tripcode == expected) as c_int,
+        Err(e) => e,
+    }
+}
+
+/// A static, NUL-terminated description of an error code.
+#[no_mangle]
+pub extern "C" fn trip_strerror(code: c_int) -> *const c_char {
+    let message: &'static [u8] = match code {
+        TRIP_OK => b"success\0",
+        TRIP_ERR_NULL => b"null pointer\0",
+        TRIP_ERR_PROFILE => b"unknown crypt implementation in profile\0",
+        TRIP_ERR_SALT => b"salt refused by the profile's crypt implementation\0",
+        TRIP_ERR_TRIPCODE => b"not a tripcode\0",
+        _ => b"unknown error\0",
+    };
+
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    // The header is written by hand, so these tests check it against the declarations here.
+    const HEADER: &str = include_str!("../include/trip.h");
+
+    /// A Rust type's spelling in the header.
+    trait CType {
+        const NAME: &'static str;
+    }
+
+    impl CType for c_int {
+        const NAME: &'static str = "int";
+    }
+
+    impl CType for usize {
+        const NAME: &'static str = "size_t";
+    }
+
+    impl CType for *const c_char {
+        const NAME: &'static str = "const char *";
+    }
+
+    impl CType for *mut c_char {
+        const NAME: &'static str = "char *";
+    }
+
+    impl CType for *const TripProfile {
+        const NAME: &'static str = "const struct trip_profile *";
+    }
+
+    /// The header's C code, without comments or preprocessor lines, with its words and
+    /// punctuation separated by single spaces.
+    fn code() -> String {
+        let mut code = String::new();
+        let mut rest = HEADER;
+
+        while let Some(start) = rest.find("/*") {
+            code += &rest[..start];
+            rest = &rest[start + rest[start..].find("*/").unwrap() + 2..];
+        }
+
+        code += rest;
+
+        let code: Vec<&str> = code.lines().filter(|line| !line.starts_with('#')).collect();
+        let code = ["*", "(", ")", ",", ";"].iter().fold(code.join(" "), |code, punctuation| {
+            code.replace(punctuation, &format!(" {} ", punctuation))
+        });
+
+        code.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// A declaration without the name it declares, such as `const char * trip_strerror`.
+    fn without_name(declaration: &str) -> String {
+        let name = declaration.trim().rsplit(' ').next().unwrap();
+        declaration.trim()[..declaration.trim().len() - name.len()].trim().to_string()
+    }
+
+    /// The return and parameter types the header declares for function `name`.
+    fn prototype(name: &str) -> Vec<String> {
+        let code = code();
+        let at = code.find(&format!(" {} (", name)).unwrap_or_else(|| panic!("{} in trip.h", name));
+        let start = code[..at].rfind([';', '}']).unwrap() + 1;
+        let end = at + code[at..].find(')').unwrap();
+        let (head, params) = code[start..end].split_once('(').unwrap();
+
+        let mut types = vec![without_name(head)];
+        types.extend(params.split(',').map(without_name));
+        types
+    }
+
+    fn types(types: &[&str]) -> Vec<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn header_functions() {
+        fn compute_or_verify<A: CType, B: CType, C: CType, D: CType, R: CType>(
+            _: unsafe extern "C" fn(A, B, C, D) -> R,
+        ) -> Vec<String> {
+            types(&[R::NAME, A::NAME, B::NAME, C::NAME, D::NAME])
+        }
+
+        fn strerror<A: CType, R: CType>(_: extern "C" fn(A) -> R) -> Vec<String> {
+            types(&[R::NAME, A::NAME])
+        }
+
+        assert_eq!(prototype("trip_compute"), compute_or_verify(trip_compute));
+        assert_eq!(prototype("trip_verify"), compute_or_verify(trip_verify));
+        assert_eq!(prototype("trip_strerror"), strerror(trip_strerror));
+    }
+
+    #[test]
+    fn header_profile() {
+        fn field<T: CType>(name: &str, _: &T, offset: usize) -> (usize, String) {
+            (offset, format!("{} {}", T::NAME, name))
+        }
+
+        // Destructured so that a field added here fails to compile until it is added below.
+        let TripProfile { map_salt, compat } = TripProfile { map_salt: 0, compat: 0 };
+
+        let mut fields = vec![
+            field("map_salt", &map_salt, mem::offset_of!(TripProfile, map_salt)),
+            field("compat", &compat, mem::offset_of!(TripProfile, compat)),
+        ];
+
+        fields.sort();
+        let fields: Vec<String> = fields.into_iter().map(|(_, field)| field).collect();
+
+        let code = code();
+        let start = code.find("struct trip_profile {").unwrap() + "struct trip_profile {".len();
+        let end = start + code[start..].find('}').unwrap();
+        let declared: Vec<String> = code[start..end]
+            .split(';')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        assert_eq!(declared, fields);
+        assert_eq!(mem::size_of::<TripProfile>(), fields.len() * mem::size_of::<c_int>());
+    }
+
+    #[test]
+    fn header_constants() {
+        let mut defined = Vec::new();
+
+        for line in HEADER.lines() {
+            let mut words = line.split_whitespace();
+
+            let name = match (words.next(), words.next()) {
+                (Some("#define"), Some(name)) => name,
+                _ => continue,
+            };
+
+            let value = words
+                .next()
+                .map(|value| value.trim_start_matches('(').trim_end_matches(')'));
+
+            if let Some(Ok(value)) = value.map(str::parse::<i64>) {
+                defined.push((name, value));
+            }
+        }
+
+        for &(name, value) in CONSTANTS {
+            assert_eq!(
+                defined.iter().find(|&&(defined, _)| defined == name),
+                Some(&(name, value)),
+                "{} in trip.h",
+                name
+            );
+        }
+
+        for &(name, _) in &defined {
+            assert!(
+                CONSTANTS.iter().any(|&(constant, _)| constant == name),
+                "{} in lib.rs",
+                name
+            );
+        }
+    }
+}
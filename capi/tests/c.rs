@@ -0,0 +1,30 @@
+//! Builds `trip_test.c` against `include/trip.h` and the static library, and runs it.
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = common::lib_dir();
+    let program = lib_dir.join("trip_test");
+    let cc = common::program("CC", "cc");
+
+    common::run(
+        &cc,
+        Command::new(&cc)
+            .arg("-Wall")
+            .arg("-Werror")
+            .arg("-I")
+            .arg(manifest_dir.join("include"))
+            .arg(manifest_dir.join("tests/trip_test.c"))
+            .arg(lib_dir.join("libtrip.a"))
+            .args(["-lpthread", "-ldl", "-lm", "-o"])
+            .arg(&program),
+    );
+
+    common::run("trip_test", &mut Command::new(&program));
+}
@@ -0,0 +1,65 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "trip.h"
+
+static int failures;
+
+#define CHECK(cond)                                                            \
+	do {                                                                   \
+		if (!(cond)) {                                                 \
+			fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__,     \
+			        #cond);                                        \
+			failures++;                                            \
+		}                                                              \
+	} while (0)
+
+static int compute(const char *password, const struct trip_profile *profile,
+                   char *out)
+{
+	return trip_compute(password, strlen(password), profile, out);
+}
+
+int main(void)
+{
+	struct trip_profile two_channel = TRIP_PROFILE_2CHANNEL;
+	struct trip_profile raw = { 0, TRIP_COMPAT_FREESEC };
+	struct trip_profile ufc = { 0, TRIP_COMPAT_UFC };
+	struct trip_profile unknown = { 1, 42 };
+	char out[TRIP_TRIPCODE_SIZE];
+
+	CHECK(compute("foofoofo", NULL, out) == TRIP_OK);
+	CHECK(strcmp(out, "vctoKCJ4Fk") == 0);
+	CHECK(compute("foofoofo", &two_channel, out) == TRIP_OK);
+	CHECK(strcmp(out, "vctoKCJ4Fk") == 0);
+
+	/* Only the first eight bytes are used. */
+	CHECK(compute("tripcodes", NULL, out) == TRIP_OK);
+	CHECK(strcmp(out, "3GqYIJ3Obs") == 0);
+	CHECK(trip_compute("a\0b", 3, NULL, out) == TRIP_OK);
+	CHECK(strcmp(out, "ZnBI2EKkq.") == 0);
+
+	/* 2channel maps the salt ":;" to "AB", FreeSec refuses it unmapped. */
+	CHECK(compute("a:;", NULL, out) == TRIP_OK);
+	CHECK(strcmp(out, "6FsyncvrHk") == 0);
+	CHECK(compute("a:;", &raw, out) == TRIP_ERR_SALT);
+	CHECK(compute("a:;", &ufc, out) == TRIP_OK);
+	CHECK(compute("a:;", &unknown, out) == TRIP_ERR_PROFILE);
+
+	CHECK(trip_verify("password", 8, NULL, "ozOtJW9BFA") == 1);
+	CHECK(trip_verify("password", 8, NULL, "ozOtJW9BFB") == 0);
+	CHECK(trip_verify("password", 8, NULL, "ozOtJW9BF") == TRIP_ERR_TRIPCODE);
+	CHECK(trip_verify("password", 8, NULL, "ozOtJW9BF*") == TRIP_ERR_TRIPCODE);
+	CHECK(trip_verify("a:;", 3, &raw, "6FsyncvrHk") == TRIP_ERR_SALT);
+
+	CHECK(trip_compute(NULL, 0, NULL, out) == TRIP_OK);
+	CHECK(trip_compute(NULL, 1, NULL, out) == TRIP_ERR_NULL);
+	CHECK(trip_compute("a", 1, NULL, NULL) == TRIP_ERR_NULL);
+	CHECK(trip_verify("a", 1, NULL, NULL) == TRIP_ERR_NULL);
+
+	CHECK(strcmp(trip_strerror(TRIP_ERR_SALT),
+	             "salt refused by the profile's crypt implementation") == 0);
+	CHECK(strcmp(trip_strerror(1), "unknown error") == 0);
+
+	return failures != 0;
+}
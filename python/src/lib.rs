@@ -0,0 +1,395 @@
+//! Python bindings, built as the `trip` extension module.
+//!
+//! Passwords are given as `bytes` or `str`, which is encoded as UTF-8, and only their first eight
+//! bytes are used.
+
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use trip::candidate::{CandidateSource, Chain, Lines, Mask, Random};
+use trip::pattern::Patterns;
+use trip::score;
+use trip::search::{Limits, State, Stop};
+use trip::trip::{trip_with, Compat, Profile};
+use trip::{Password, Tripcode};
+
+/// How long `Search.__next__` waits for a match before checking for signals.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The number of matches found but not yet taken which stalls the workers.
+const QUEUE_SIZE: usize = 1024;
+
+/// A password given as `bytes` or `str`.
+fn password(password: &Bound<'_, PyAny>) -> PyResult<Password> {
+    if let Ok(bytes) = password.extract::<Vec<u8>>() {
+        return Ok(Password::from(bytes));
+    }
+
+    match password.extract::<String>() {
+        Ok(s) => Ok(Password::from(s.as_str())),
+        Err(_) => Err(PyTypeError::new_err("password must be bytes or str")),
+    }
+}
+
+fn compat_name(compat: Compat) -> &'static str {
+    match compat {
+        Compat::FreeSec => "freesec",
+        Compat::Strict => "strict",
+        Compat::Ufc => "ufc",
+        Compat::UfcUnsigned => "ufc-unsigned",
+        Compat::Bsd => "bsd",
+    }
+}
+
+/// How a board turns a password into a tripcode: whether it maps salt characters into
+/// `./0-9A-Za-z` as 2channel does, and which crypt(3) implementation it runs on.
+#[pyclass(frozen, eq, skip_from_py_object, name = "Profile", module = "trip")]
+#[derive(Clone, PartialEq)]
+struct PyProfile(Profile);
+
+#[pymethods]
+impl PyProfile {
+    #[new]
+    #[pyo3(signature = (map_salt = true, compat = "freesec"))]
+    fn new(map_salt: bool, compat: &str) -> PyResult<PyProfile> {
+        let compat: Compat = compat.parse().map_err(|e| PyValueError::new_err(format!("{}", e)))?;
+        Ok(PyProfile(Profile { map_salt, compat }))
+    }
+
+    #[getter]
+    fn map_salt(&self) -> bool {
+        self.0.map_salt
+    }
+
+    #[getter]
+    fn compat(&self) -> &'static str {
+        compat_name(self.0.compat)
+    }
+
+    fn __repr__(&self) -> String {
+        let map_salt = if self.0.map_salt { "True" } else { "False" };
+        format!("Profile(map_salt={}, compat='{}')", map_salt, compat_name(self.0.compat))
+    }
+}
+
+fn profile_or_default(profile: Option<PyRef<'_, PyProfile>>) -> Profile {
+    profile.map_or(Profile::TWO_CHANNEL, |profile| profile.0)
+}
+
+fn tripcode(passwd: &Password, profile: Profile) -> PyResult<Tripcode> {
+    trip_with(*passwd.key(), profile).ok_or_else(|| {
+        PyValueError::new_err("salt refused by the profile's crypt implementation")
+    })
+}
+
+/// trip(password, profile=None)
+/// --
+///
+/// The tripcode of a password, under 2channel's rules unless another profile is given.
+#[pyfunction]
+#[pyo3(name = "trip", signature = (password, profile = None))]
+fn py_trip(
+    #[pyo3(from_py_with = password)] password: Password,
+    profile: Option<PyRef<'_, PyProfile>>,
+) -> PyResult<String> {
+    Ok(tripcode(&password, profile_or_default(profile))?.to_string())
+}
+
+/// verify(password, tripcode, profile=None)
+/// --
+///
+/// Whether a password generates a tripcode.
+#[pyfunction]
+#[pyo3(signature = (password, tripcode, profile = None))]
+fn verify(
+    #[pyo3(from_py_with = password)] password: Password,
+    tripcode: &str,
+    profile: Option<PyRef<'_, PyProfile>>,
+) -> PyResult<bool> {
+    let tripcode: Tripcode =
+        tripcode.parse().map_err(|e| PyValueError::new_err(format!("{}", e)))?;
+
+    Ok(self::tripcode(&password, profile_or_default(profile))? == tripcode)
+}
+
+/// A tripcode found by a search.
+#[pyclass(frozen, get_all, name = "Match", module = "trip")]
+struct PyMatch {
+    password: Vec<u8>,
+    tripcode: String,
+    /// The first pattern found in the tripcode.
+    pattern: String,
+    offset: usize,
+    score: u32,
+    /// The index of the thread which found the match.
+    worker: usize,
+}
+
+#[pymethods]
+impl PyMatch {
+    fn __repr__(&self) -> String {
+        format!(
+            "Match(password={:?}, tripcode='{}', pattern={:?}, offset={}, score={}, worker={})",
+            String::from_utf8_lossy(&self.password),
+            self.tripcode,
+            self.pattern,
+            self.offset,
+            self.score,
+            self.worker
+        )
+    }
+}
+
+/// A search running in background threads, which yields matches as they are found.
+///
+/// The search stops once its candidates or one of its limits are exhausted, or when it is
+/// cancelled, either by `cancel` or by a signal such as KeyboardInterrupt while waiting for a
+/// match.
+#[pyclass(frozen, name = "Search", module = "trip")]
+struct PySearch {
+    state: Arc<State>,
+    // Only locked with the GIL released, so that a thread waiting for either never holds up one
+    // which has them and wants the GIL back.
+    matches: Mutex<Option<Receiver<PyMatch>>>,
+    threads: Mutex<Vec<JoinHandle<io::Result<()>>>>,
+    done: AtomicBool,
+}
+
+impl PySearch {
+    /// Waits for the workers to exit, which they do once they have nowhere to send matches,
+    /// returning the first error one of them stopped the search with.
+    fn finish(&self, py: Python<'_>) -> PyResult<()> {
+        let result = py.detach(|| {
+            self.matches.lock().unwrap().take();
+            let mut result = Ok(());
+
+            for thread in self.threads.lock().unwrap().drain(..) {
+                let joined = match thread.join() {
+                    Ok(joined) => joined.map_err(PyErr::from),
+                    Err(_) => Err(PyRuntimeError::new_err("a search thread panicked")),
+                };
+
+                if result.is_ok() {
+                    result = joined;
+                }
+            }
+
+            result
+        });
+
+        self.done.store(true, Ordering::Relaxed);
+        result
+    }
+}
+
+#[pymethods]
+impl PySearch {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyMatch>> {
+        loop {
+            if self.state.aborted() && self.state.reason() == Stop::Cancelled {
+                self.finish(py)?;
+                return Ok(None);
+            }
+
+            let result = py.detach(|| {
+                self.matches.lock().unwrap()
+                    .as_mut()
+                    .map(|matches| matches.recv_timeout(POLL_INTERVAL))
+            });
+
+            match result {
+                Some(Ok(m)) => return Ok(Some(m)),
+                Some(Err(RecvTimeoutError::Timeout)) => {
+                    if let Err(e) = py.check_signals() {
+                        self.state.stop(Stop::Cancelled);
+                        let _ = self.finish(py);
+                        return Err(e);
+                    }
+                }
+                Some(Err(RecvTimeoutError::Disconnected)) | None => {
+                    self.finish(py)?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Stops the search. Matches not yet taken are discarded.
+    fn cancel(&self, py: Python<'_>) -> PyResult<()> {
+        self.state.stop(Stop::Cancelled);
+        self.finish(py)
+    }
+
+    /// The number of candidates tried so far.
+    #[getter]
+    fn candidates(&self) -> u64 {
+        self.state.candidates()
+    }
+
+    /// Why the search stopped, or None while it is running.
+    #[getter]
+    fn stopped(&self) -> Option<String> {
+        if self.done.load(Ordering::Relaxed) {
+            Some(self.state.reason().to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for PySearch {
+    fn drop(&mut self) {
+        self.state.stop(Stop::Cancelled);
+        self.matches.get_mut().unwrap().take();
+
+        // Errors, and panics, have nowhere to be raised.
+        for thread in self.threads.get_mut().unwrap().drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// search(patterns, *, threads=1, exhaustive=False, masks=None, wordlists=None, seed=None,
+///        ignore_case=False, profile=None, duration=None, max_candidates=None, max_matches=None)
+/// --
+///
+/// Starts searching for tripcodes containing any of the patterns, returning a Search which
+/// yields each Match found.
+///
+/// Candidates are every password over the tripcode alphabet if exhaustive is set, then those
+/// matching each mask, then each line of each wordlist, or random passwords if none are given.
+/// duration is in seconds.
+#[pyfunction]
+#[pyo3(signature = (
+    patterns,
+    *,
+    threads = 1,
+    exhaustive = false,
+    masks = None,
+    wordlists = None,
+    seed = None,
+    ignore_case = false,
+    profile = None,
+    duration = None,
+    max_candidates = None,
+    max_matches = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn search(
+    patterns: Vec<String>,
+    threads: usize,
+    exhaustive: bool,
+    masks: Option<Vec<String>>,
+    wordlists: Option<Vec<String>>,
+    seed: Option<u64>,
+    ignore_case: bool,
+    profile: Option<PyRef<'_, PyProfile>>,
+    duration: Option<f64>,
+    max_candidates: Option<u64>,
+    max_matches: Option<u64>,
+) -> PyResult<PySearch> {
+    if threads == 0 {
+        return Err(PyValueError::new_err("threads must be at least 1"));
+    }
+
+    trip::trip::self_test().map_err(|e| {
+        PyRuntimeError::new_err(format!("self-test failed, refusing to search: {}", e))
+    })?;
+
+    let mut sources: Vec<Box<dyn CandidateSource>> = Vec::new();
+
+    if exhaustive {
+        sources.push(Box::new(Mask::exhaustive()));
+    }
+
+    for mask in masks.unwrap_or_default() {
+        let mask = Mask::parse(&mask).map_err(|e| PyValueError::new_err(format!("{}", e)))?;
+        sources.push(Box::new(mask));
+    }
+
+    for wordlist in wordlists.unwrap_or_default() {
+        sources.push(Box::new(Lines::open(wordlist)?));
+    }
+
+    let source: Box<dyn CandidateSource> = match seed {
+        _ if !sources.is_empty() && seed.is_some() => {
+            return Err(PyValueError::new_err("seed only applies to random searches"));
+        }
+        Some(seed) => Box::new(Random::seeded(seed)),
+        None if sources.is_empty() => Box::new(Random::new()),
+        None => Box::new(Chain::new(sources)),
+    };
+
+    let duration = match duration {
+        Some(secs) => Some(Duration::try_from_secs_f64(secs).map_err(|_| {
+            PyValueError::new_err(format!("invalid duration {}", secs))
+        })?),
+        None => None,
+    };
+
+    let limits = Limits { duration, candidates: max_candidates, matches: max_matches };
+    let state = Arc::new(State::new(limits, threads));
+    let patterns = Arc::new(Patterns::new(patterns, ignore_case));
+    let profile = profile_or_default(profile);
+    let (tx, rx) = mpsc::sync_channel(QUEUE_SIZE);
+
+    let handles = source.split(threads)
+        .into_iter()
+        .enumerate()
+        .map(|(worker, source)| {
+            let (state, patterns, tx) = (state.clone(), patterns.clone(), tx.clone());
+
+            thread::spawn(move || -> io::Result<()> {
+                trip::search::run(worker, source, &patterns, profile, &state, |found| {
+                    if !state.record_match() {
+                        return Ok(());
+                    }
+
+                    let tripcode = found.tripcode.as_str();
+
+                    let m = PyMatch {
+                        password: Password::from_key(found.passwd).as_bytes().to_vec(),
+                        tripcode: tripcode.to_string(),
+                        pattern: found.pattern.to_string(),
+                        offset: found.offset,
+                        score: score::score(&patterns, tripcode),
+                        worker,
+                    };
+
+                    // Only fails once the search is cancelled, which ends the loop.
+                    let _ = tx.send(m);
+                    Ok(())
+                })
+            })
+        })
+        .collect();
+
+    Ok(PySearch {
+        state,
+        matches: Mutex::new(Some(rx)),
+        threads: Mutex::new(handles),
+        done: AtomicBool::new(false),
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "trip")]
+fn trip_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyProfile>()?;
+    m.add_class::<PyMatch>()?;
+    m.add_class::<PySearch>()?;
+    m.add("TWO_CHANNEL", PyProfile(Profile::TWO_CHANNEL))?;
+    m.add_function(wrap_pyfunction!(py_trip, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(search, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Runs `test_trip.py` against the extension module.
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The name Python imports the module from: `.pyd` on Windows, and `.so` elsewhere, macOS
+/// included.
+const MODULE: &str = if cfg!(windows) { "trip.pyd" } else { "trip.so" };
+
+#[test]
+fn python_tests() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = common::lib_dir();
+    let module_dir = lib_dir.join("python");
+    let library = format!("{}trip_python{}", DLL_PREFIX, DLL_SUFFIX);
+    fs::create_dir_all(&module_dir).unwrap();
+    fs::copy(lib_dir.join(library), module_dir.join(MODULE)).unwrap();
+    let python = common::program("PYTHON", "python3");
+
+    common::run(
+        &python,
+        Command::new(&python)
+            .arg(manifest_dir.join("tests/test_trip.py"))
+            .env("PYTHONPATH", &module_dir),
+    );
+}
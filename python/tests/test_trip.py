@@ -0,0 +1,78 @@
+import threading
+import time
+import unittest
+
+import trip
+
+
+class TestTrip(unittest.TestCase):
+    def test_trip(self):
+        self.assertEqual(trip.trip(b"foofoofo"), "vctoKCJ4Fk")
+        self.assertEqual(trip.trip("foofoofo"), "vctoKCJ4Fk")
+        self.assertEqual(trip.trip("tripcodes"), "3GqYIJ3Obs")
+        self.assertEqual(trip.trip(b""), "8NBuQ4l6uQ")
+        self.assertEqual(trip.trip("あ"), "VTkshKs7a.")
+
+    def test_profiles(self):
+        self.assertEqual(trip.TWO_CHANNEL, trip.Profile())
+        self.assertEqual(trip.trip(b"a:;", trip.TWO_CHANNEL), "6FsyncvrHk")
+
+        raw = trip.Profile(map_salt=False)
+        self.assertEqual(raw.compat, "freesec")
+        self.assertRaises(ValueError, trip.trip, b"a:;", raw)
+        trip.trip(b"a:;", trip.Profile(map_salt=False, compat="ufc"))
+        self.assertRaises(ValueError, trip.Profile, compat="glibc")
+
+    def test_verify(self):
+        self.assertTrue(trip.verify(b"password", "ozOtJW9BFA"))
+        self.assertFalse(trip.verify(b"password", "ozOtJW9BFB"))
+        self.assertRaises(ValueError, trip.verify, b"password", "ozOtJW9BF")
+        self.assertRaises(ValueError, trip.verify, b"password", "ozOtJW9BF*")
+        self.assertTrue(trip.verify("password", "!ozOtJW9BFA"))
+
+    def test_search(self):
+        search = trip.search(["Fk"], threads=2, masks=["foofoof?l"])
+        matches = list(search)
+        self.assertIn(b"foofoofo", [m.password for m in matches])
+
+        for m in matches:
+            self.assertEqual(trip.trip(m.password), m.tripcode)
+            self.assertEqual(m.tripcode.index("Fk"), m.offset)
+
+        self.assertEqual(search.candidates, 26)
+        self.assertEqual(search.stopped, "every candidate was tried")
+
+    def test_seeded_search(self):
+        run = lambda: [m.password for m in trip.search(["a"], seed=7, max_matches=5)]
+        self.assertEqual(run(), run())
+        self.assertEqual(len(run()), 5)
+
+    def test_cancel(self):
+        search = trip.search(["thiswontmatch"], threads=2)
+        self.assertIsNone(search.stopped)
+        threading.Timer(0.2, search.cancel).start()
+        start = time.monotonic()
+        self.assertEqual(list(search), [])
+        self.assertLess(time.monotonic() - start, 5)
+        self.assertEqual(search.stopped, "the search was cancelled")
+        self.assertGreater(search.candidates, 0)
+
+    def test_duration(self):
+        search = trip.search(["thiswontmatch"], duration=0.2)
+        self.assertEqual(list(search), [])
+        self.assertEqual(search.stopped, "the time limit was reached")
+
+    def test_invalid(self):
+        self.assertRaises(ValueError, trip.search, ["a"], threads=0)
+        self.assertRaises(ValueError, trip.search, ["a"], masks=["?x"])
+        self.assertRaises(ValueError, trip.search, ["a"], exhaustive=True, seed=1)
+        self.assertRaises(OSError, trip.search, ["a"], wordlists=["/nonexistent"])
+
+    def test_read_error(self):
+        search = trip.search(["a"], wordlists=["/"])
+        self.assertRaises(OSError, list, search)
+        self.assertEqual(search.stopped, "an error occurred")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,574 @@
+use rng::{Counter, Generator};
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub const SAMPLES: [u8; 64] = *b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The mask of every eight-character password over the tripcode alphabet.
+pub const EXHAUSTIVE: &str = "?t?t?t?t?t?t?t?t";
+
+/// A generator of candidate passwords.
+///
+/// Passwords shorter than eight bytes are padded with NUL bytes, which `trip::trip` treats as the
+/// end of the password.
+pub trait CandidateSource: Send {
+    /// Appends at most `max` candidates to `batch`. Returns `false` once the source is exhausted,
+    /// or an error if its candidates could not be read.
+    fn fill(&mut self, batch: &mut Vec<[u8; 8]>, max: usize) -> io::Result<bool>;
+
+    /// Divides the source into `n` sources which together generate the same candidates, one for
+    /// each worker.
+    fn split(self: Box<Self>, n: usize) -> Vec<Box<dyn CandidateSource>>;
+
+    /// The number of candidates the source has yet to generate, if known.
+    fn size_hint(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[inline(always)]
+pub fn rand_passwd<G: Generator>(rng: &mut G) -> [u8; 8] {
+    let var = rng.next_u64();
+
+    [
+        SAMPLES[( var        & 0x3f) as usize],
+        SAMPLES[((var >>  8) & 0x3f) as usize],
+        SAMPLES[((var >> 16) & 0x3f) as usize],
+        SAMPLES[((var >> 24) & 0x3f) as usize],
+        SAMPLES[((var >> 32) & 0x3f) as usize],
+        SAMPLES[((var >> 40) & 0x3f) as usize],
+        SAMPLES[((var >> 48) & 0x3f) as usize],
+        SAMPLES[((var >> 56) & 0x3f) as usize],
+    ]
+}
+
+/// Uniformly random eight-character passwords. Never exhausted.
+pub struct Random {
+    rng: Counter,
+    seed: Option<u64>,
+}
+
+impl Random {
+    pub fn new() -> Random {
+        Random { rng: Counter::from_entropy(), seed: None }
+    }
+
+    /// Random passwords generated deterministically from `seed`. Each worker the source is split
+    /// between is given its own stream derived from the seed and the worker's index.
+    pub fn seeded(seed: u64) -> Random {
+        Random::stream(seed, 0)
+    }
+
+    fn stream(seed: u64, index: u64) -> Random {
+        Random { rng: Counter::stream(seed, index), seed: Some(seed) }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Random {
+        Random::new()
+    }
+}
+
+impl CandidateSource for Random {
+    fn fill(&mut self, batch: &mut Vec<[u8; 8]>, max: usize) -> io::Result<bool> {
+        for _ in 0..max {
+            batch.push(rand_passwd(&mut self.rng));
+        }
+
+        Ok(true)
+    }
+
+    fn split(self: Box<Self>, n: usize) -> Vec<Box<dyn CandidateSource>> {
+        (0..n as u64)
+            .map(|i| match self.seed {
+                Some(seed) => Box::new(Random::stream(seed, i + 1)) as Box<dyn CandidateSource>,
+                None => Box::new(Random::new()),
+            })
+            .collect()
+    }
+}
+
+/// Every password matching a mask, in order, with the last position varying fastest.
+///
+/// A mask is a sequence of up to eight positions, each either a literal byte or one of the
+/// following character classes:
+///
+/// * `?l`: `abcdefghijklmnopqrstuvwxyz`
+/// * `?u`: `ABCDEFGHIJKLMNOPQRSTUVWXYZ`
+/// * `?d`: `0123456789`
+/// * `?s`: printable ASCII punctuation and space
+/// * `?a`: `?l?u?d?s`
+/// * `?t`: the 64 characters of the tripcode alphabet
+/// * `??`: a literal `?`
+#[derive(Clone)]
+pub struct Mask {
+    charsets: Vec<Vec<u8>>,
+    next: u64,
+    end: u64,
+}
+
+/// The error returned when a mask is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseMaskError {
+    /// The mask has no positions, or more than eight.
+    Length(usize),
+    EmptyPosition,
+    /// The mask has more candidates than fit in a `u64`.
+    TooLarge,
+    /// `?` is followed by a character that isn't a class.
+    UnknownClass(char),
+    TrailingQuestionMark,
+}
+
+impl fmt::Display for ParseMaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseMaskError::Length(len) => {
+                write!(f, "mask must have between 1 and 8 positions, not {}", len)
+            }
+            ParseMaskError::EmptyPosition => f.write_str("mask position has no characters"),
+            ParseMaskError::TooLarge => f.write_str("mask keyspace is too large"),
+            ParseMaskError::UnknownClass(c) => write!(f, "unknown mask character class ?{}", c),
+            ParseMaskError::TrailingQuestionMark => f.write_str("mask ends with ?"),
+        }
+    }
+}
+
+impl error::Error for ParseMaskError {}
+
+impl Mask {
+    pub fn new(charsets: Vec<Vec<u8>>) -> Result<Mask, ParseMaskError> {
+        if charsets.is_empty() || charsets.len() > 8 {
+            return Err(ParseMaskError::Length(charsets.len()));
+        }
+
+        let mut end = 1u64;
+
+        for charset in &charsets {
+            if charset.is_empty() {
+                return Err(ParseMaskError::EmptyPosition);
+            }
+
+            end = end.checked_mul(charset.len() as u64).ok_or(ParseMaskError::TooLarge)?;
+        }
+
+        Ok(Mask { charsets, next: 0, end })
+    }
+
+    /// Restricts the source to the candidates numbered `start` to `end`, counting from zero in
+    /// the order they would otherwise be generated. The range is clamped to the candidates the
+    /// mask has yet to generate.
+    pub fn range(self, start: u64, end: u64) -> Mask {
+        let end = end.min(self.end - self.next);
+        let start = start.min(end);
+        Mask { next: self.next + start, end: self.next + end, ..self }
+    }
+
+    /// Part `k` of `n` roughly equal parts of the passwords the mask has yet to generate, counting
+    /// from zero. Parts beyond the last are empty.
+    pub fn part(self, k: u64, n: u64) -> Mask {
+        if k >= n {
+            return self.range(0, 0);
+        }
+
+        let len = self.end - self.next;
+        let start = len / n * k + (len % n).min(k);
+        let end = len / n * (k + 1) + (len % n).min(k + 1);
+        self.range(start, end)
+    }
+
+    /// Every eight-character password over the tripcode alphabet.
+    pub fn exhaustive() -> Mask {
+        Mask::parse(EXHAUSTIVE).unwrap()
+    }
+
+    pub fn parse(mask: &str) -> Result<Mask, ParseMaskError> {
+        let mut charsets = Vec::new();
+        let mut bytes = mask.bytes();
+
+        while let Some(b) = bytes.next() {
+            if b != b'?' {
+                charsets.push(vec![b]);
+                continue;
+            }
+
+            let lower: Vec<u8> = (b'a'..b'z' + 1).collect();
+            let upper: Vec<u8> = (b'A'..b'Z' + 1).collect();
+            let digit: Vec<u8> = (b'0'..b'9' + 1).collect();
+            let special: Vec<u8> = (b' '..b'~' + 1)
+                .filter(|c| !(*c as char).is_ascii_alphanumeric())
+                .collect();
+
+            charsets.push(match bytes.next() {
+                Some(b'l') => lower,
+                Some(b'u') => upper,
+                Some(b'd') => digit,
+                Some(b's') => special,
+                Some(b'a') => [lower, upper, digit, special].concat(),
+                Some(b't') => SAMPLES.to_vec(),
+                Some(b'?') => vec![b'?'],
+                Some(c) => return Err(ParseMaskError::UnknownClass(c as char)),
+                None => return Err(ParseMaskError::TrailingQuestionMark),
+            });
+        }
+
+        Mask::new(charsets)
+    }
+
+    fn passwd(&self, mut index: u64) -> [u8; 8] {
+        let mut passwd = [0u8; 8];
+
+        for (i, charset) in self.charsets.iter().enumerate().rev() {
+            let radix = charset.len() as u64;
+            passwd[i] = charset[(index % radix) as usize];
+            index /= radix;
+        }
+
+        passwd
+    }
+}
+
+impl CandidateSource for Mask {
+    fn fill(&mut self, batch: &mut Vec<[u8; 8]>, max: usize) -> io::Result<bool> {
+        let end = self.end.min(self.next.saturating_add(max as u64));
+
+        for index in self.next..end {
+            batch.push(self.passwd(index));
+        }
+
+        self.next = end;
+        Ok(self.next < self.end)
+    }
+
+    fn split(self: Box<Self>, n: usize) -> Vec<Box<dyn CandidateSource>> {
+        (0..n as u64)
+            .map(|i| Box::new(self.clone().part(i, n as u64)) as Box<dyn CandidateSource>)
+            .collect()
+    }
+
+    fn size_hint(&self) -> Option<u64> {
+        Some(self.end - self.next)
+    }
+}
+
+/// One password per line from a reader shared between all workers. Passwords longer than eight
+/// bytes are truncated and empty lines are skipped.
+///
+/// A batch ends early rather than wait for more input once it has a candidate, so that the
+/// candidates of a slow stream are tried as they arrive.
+pub struct Lines {
+    reader: Arc<Mutex<BufReader<Box<dyn Read + Send>>>>,
+    name: Option<Arc<str>>,
+}
+
+impl Lines {
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Lines {
+        let reader: Box<dyn Read + Send> = Box::new(reader);
+        Lines { reader: Arc::new(Mutex::new(BufReader::new(reader))), name: None }
+    }
+
+    /// Lines whose read errors are `ReadError`s naming their source as `name`.
+    pub fn named<R: Read + Send + 'static>(name: &str, reader: R) -> Lines {
+        Lines { name: Some(name.into()), ..Lines::new(reader) }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Lines> {
+        let path = path.as_ref();
+        File::open(path).map(|file| Lines::named(&path.display().to_string(), file))
+    }
+
+    pub fn stdin() -> Lines {
+        Lines::named("standard input", io::stdin())
+    }
+}
+
+/// An error reading the named source of `Lines`, carried by the `io::Error` it returns.
+#[derive(Debug)]
+pub struct ReadError {
+    pub name: String,
+    pub error: io::Error,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.error)
+    }
+}
+
+impl error::Error for ReadError {}
+
+/// Reads a line into `passwd`, padded with NUL bytes, keeping only the eight bytes crypt(3) uses
+/// so that no other copy of the line is made. Returns the length of the line without its line
+/// ending, or `None` at the end of the input.
+fn read_passwd<R: BufRead>(reader: &mut R, passwd: &mut [u8; 8]) -> io::Result<Option<usize>> {
+    let mut len = 0;
+    // The carriage returns ending the line so far, which belong to its line ending.
+    let mut returns = 0;
+    let mut read = false;
+
+    loop {
+        let buf = reader.fill_buf()?;
+
+        if buf.is_empty() {
+            break;
+        }
+
+        read = true;
+        let end = buf.iter().position(|&c| c == b'\n');
+
+        for &c in &buf[..end.unwrap_or(buf.len())] {
+            if len < passwd.len() {
+                passwd[len] = c;
+            }
+
+            len += 1;
+            returns = if c == b'\r' { returns + 1 } else { 0 };
+        }
+
+        let used = end.map_or(buf.len(), |end| end + 1);
+        reader.consume(used);
+
+        if end.is_some() {
+            break;
+        }
+    }
+
+    let len = len - returns;
+
+    for c in passwd.iter_mut().skip(len) {
+        *c = 0;
+    }
+
+    Ok(if read { Some(len) } else { None })
+}
+
+impl CandidateSource for Lines {
+    fn fill(&mut self, batch: &mut Vec<[u8; 8]>, max: usize) -> io::Result<bool> {
+        let mut reader = self.reader.lock().unwrap();
+        let mut added = 0;
+
+        while added < max {
+            // Only a buffered line is certain to be read without blocking.
+            if added > 0 && !reader.buffer().contains(&b'\n') {
+                break;
+            }
+
+            let mut passwd = [0u8; 8];
+            let read = read_passwd(&mut *reader, &mut passwd).map_err(|error| match self.name {
+                Some(ref name) => {
+                    io::Error::new(error.kind(), ReadError { name: name.to_string(), error })
+                }
+                None => error,
+            })?;
+
+            match read {
+                Some(0) => continue,
+                Some(_) => {
+                    batch.push(passwd);
+                    added += 1;
+                }
+                None => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn split(self: Box<Self>, n: usize) -> Vec<Box<dyn CandidateSource>> {
+        (0..n)
+            .map(|_| {
+                let lines = Lines { reader: self.reader.clone(), name: self.name.clone() };
+                Box::new(lines) as Box<dyn CandidateSource>
+            })
+            .collect()
+    }
+}
+
+/// The candidates of each source in turn.
+pub struct Chain {
+    sources: Vec<Box<dyn CandidateSource>>,
+}
+
+impl Chain {
+    pub fn new(sources: Vec<Box<dyn CandidateSource>>) -> Chain {
+        Chain { sources }
+    }
+}
+
+impl CandidateSource for Chain {
+    fn fill(&mut self, batch: &mut Vec<[u8; 8]>, max: usize) -> io::Result<bool> {
+        let start = batch.len();
+
+        while !self.sources.is_empty() && batch.len() - start < max {
+            let added = batch.len() - start;
+
+            if self.sources[0].fill(batch, max - added)? {
+                return Ok(true);
+            }
+
+            self.sources.remove(0);
+        }
+
+        Ok(!self.sources.is_empty())
+    }
+
+    fn split(self: Box<Self>, n: usize) -> Vec<Box<dyn CandidateSource>> {
+        let mut chains: Vec<_> = (0..n).map(|_| Vec::new()).collect();
+
+        for source in self.sources {
+            for (chain, part) in chains.iter_mut().zip(source.split(n)) {
+                chain.push(part);
+            }
+        }
+
+        chains
+            .into_iter()
+            .map(|sources| Box::new(Chain::new(sources)) as Box<dyn CandidateSource>)
+            .collect()
+    }
+
+    fn size_hint(&self) -> Option<u64> {
+        let mut sum = 0u64;
+
+        for source in &self.sources {
+            sum = sum.checked_add(source.size_hint()?)?;
+        }
+
+        Some(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn drain(source: &mut Box<dyn CandidateSource>) -> Vec<[u8; 8]> {
+        let mut batch = Vec::new();
+        while source.fill(&mut batch, 7).unwrap() {}
+        batch
+    }
+
+    #[test]
+    fn mask_split_covers_keyspace() {
+        let mut whole: Box<dyn CandidateSource> = Box::new(Mask::parse("a?d?l").unwrap());
+        let expected = drain(&mut whole);
+        assert_eq!(expected.len(), 260);
+        assert_eq!(&expected[0], b"a0a\0\0\0\0\0");
+        assert_eq!(&expected[259], b"a9z\0\0\0\0\0");
+
+        let parts = Box::new(Mask::parse("a?d?l").unwrap()).split(3);
+        let actual: Vec<_> = parts.into_iter().flat_map(|mut p| drain(&mut p)).collect();
+        assert_eq!(actual, expected);
+
+        let part = Box::new(Mask::parse("a?d?l").unwrap().part(1, 3)).split(2);
+        let actual: Vec<_> = part.into_iter().flat_map(|mut p| drain(&mut p)).collect();
+        assert_eq!(actual, &expected[87..174]);
+
+        let mut part: Box<dyn CandidateSource> = Box::new(Mask::parse("a?d?l").unwrap().part(3, 3));
+        assert!(drain(&mut part).is_empty());
+        let mut range: Box<dyn CandidateSource> =
+            Box::new(Mask::parse("a?d?l").unwrap().range(250, 300));
+        assert_eq!(drain(&mut range), &expected[250..]);
+    }
+
+    #[test]
+    fn mask_errors() {
+        assert_eq!(Mask::parse("").err(), Some(ParseMaskError::Length(0)));
+        assert_eq!(Mask::parse("?a?a?a?a?a?a?a?a?a").err(), Some(ParseMaskError::Length(9)));
+        assert_eq!(Mask::new(vec![vec![]]).err(), Some(ParseMaskError::EmptyPosition));
+        assert_eq!(Mask::new(vec![(0..=255).collect(); 8]).err(), Some(ParseMaskError::TooLarge));
+        assert_eq!(Mask::parse("a?x").err(), Some(ParseMaskError::UnknownClass('x')));
+        assert_eq!(Mask::parse("a?").err(), Some(ParseMaskError::TrailingQuestionMark));
+    }
+
+    #[test]
+    fn seeded_random_is_reproducible() {
+        let run = |seed| -> Vec<Vec<[u8; 8]>> {
+            Box::new(Random::seeded(seed))
+                .split(2)
+                .into_iter()
+                .map(|mut p| {
+                    let mut batch = Vec::new();
+                    p.fill(&mut batch, 4).unwrap();
+                    batch
+                })
+                .collect()
+        };
+
+        let first = run(42);
+        assert_eq!(first, run(42));
+        assert_ne!(first[0], first[1]);
+        assert_ne!(first, run(43));
+    }
+
+    #[test]
+    fn chain_split_covers_sources() {
+        let chain = Chain::new(vec![
+            Box::new(Mask::parse("?d").unwrap()),
+            Box::new(Lines::new(&b"foo\r\n\nbar\n"[..])),
+        ]);
+
+        let mut actual: Vec<_> =
+            Box::new(chain).split(4).into_iter().flat_map(|mut p| drain(&mut p)).collect();
+        actual.sort();
+        assert_eq!(actual.len(), 12);
+        assert_eq!(&actual[10], b"bar\0\0\0\0\0");
+        assert_eq!(&actual[11], b"foo\0\0\0\0\0");
+    }
+
+    #[test]
+    fn lines_stream() {
+        // Fails the test if read again before the batch is returned.
+        struct Once(Option<&'static [u8]>);
+
+        impl Read for Once {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let data = self.0.take().expect("read past the first chunk");
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+        }
+
+        let mut lines = Lines::new(Once(Some(b"foo\nbar\nba")));
+        let mut batch = Vec::new();
+        assert!(lines.fill(&mut batch, 1024).unwrap());
+        assert_eq!(batch, [*b"foo\0\0\0\0\0", *b"bar\0\0\0\0\0"]);
+    }
+
+    #[test]
+    fn lines_across_reads() {
+        // Returns the input a few bytes at a time, so that lines span reads.
+        struct Chunks(&'static [u8]);
+
+        impl Read for Chunks {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.0.len().min(buf.len()).min(3);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let input = b"passwords\nabcdefg\r\r\n\r\na\rb\r\nlast";
+        let mut lines: Box<dyn CandidateSource> = Box::new(Lines::new(Chunks(input)));
+        let expected = [*b"password", *b"abcdefg\0", *b"a\rb\0\0\0\0\0", *b"last\0\0\0\0"];
+        assert_eq!(drain(&mut lines), expected);
+    }
+
+    #[test]
+    fn read_errors_are_returned() {
+        let dir = env::temp_dir();
+        let mut lines = Lines::open(&dir).unwrap();
+        let error = lines.fill(&mut Vec::new(), 1).unwrap_err();
+        assert!(error.to_string().starts_with(&format!("{}: ", dir.display())));
+
+        let error = error.downcast::<ReadError>().unwrap();
+        assert_eq!(error.name, dir.display().to_string());
+    }
+}
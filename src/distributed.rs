@@ -0,0 +1,543 @@
+//! Searching a mask's keyspace across machines.
+//!
+//! A coordinator divides the keyspace into work units and hands them out over TCP to workers,
+//! which report matches and progress back. A unit the coordinator has not heard about for
+//! longer than a timeout, such as one held by a worker that crashed, is handed out again.
+//!
+//! The protocol is line based. On connecting, the coordinator sends the job:
+//!
+//! ```text
+//! mask <hex>
+//! pattern <hex>
+//! ignore-case
+//! heartbeat <milliseconds>
+//! ready
+//! ```
+//!
+//! with one `pattern` line for each pattern, and `ignore-case` only if patterns match regardless
+//! of case. Strings are sent as the hexadecimal encoding of their bytes. The worker then sends
+//! `request`, to which the coordinator replies `unit <id> <start> <end>`, `wait <milliseconds>`
+//! if every remaining unit is held by another worker, or `done`. While trying the candidates of a
+//! unit, the worker sends `match <id> <hex password> <tripcode>` for each match and
+//! `progress <id> <candidates tried>` at least once per heartbeat, then `complete <id>`. The
+//! coordinator checks the tripcode of each match, and drops a worker which sends a wrong one.
+
+use candidate::{CandidateSource, Mask};
+use password::Password;
+use pattern::Patterns;
+use search::{self, Limits, State};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use trip::{self, Profile};
+use tripcode::Tripcode;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What to search for.
+pub struct Job {
+    /// A mask in the syntax accepted by `Mask::parse`.
+    pub mask: String,
+    pub patterns: Vec<String>,
+    pub ignore_case: bool,
+}
+
+enum Assignment {
+    Unit(u64, u64, u64),
+    Wait,
+    Done,
+}
+
+struct Unit {
+    start: u64,
+    end: u64,
+    deadline: Instant,
+    /// The number of candidates the worker last reported trying.
+    tried: u64,
+}
+
+struct Units {
+    next: u64,
+    end: u64,
+    size: u64,
+    timeout: Duration,
+    next_id: u64,
+    outstanding: HashMap<u64, Unit>,
+    tried: u64,
+}
+
+impl Units {
+    fn assign(&mut self) -> Assignment {
+        let now = Instant::now();
+
+        let expired = self.outstanding
+            .iter()
+            .find(|&(_, unit)| unit.deadline <= now)
+            .map(|(&id, _)| id);
+
+        let (start, end) = if let Some(id) = expired {
+            let unit = self.outstanding.remove(&id).unwrap();
+            (unit.start, unit.end)
+        } else if self.next < self.end {
+            let start = self.next;
+            self.next = self.end.min(start.saturating_add(self.size));
+            (start, self.next)
+        } else if self.outstanding.is_empty() {
+            return Assignment::Done;
+        } else {
+            return Assignment::Wait;
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.outstanding.insert(id, Unit {
+            start,
+            end,
+            deadline: now + self.timeout,
+            tried: 0,
+        });
+
+        Assignment::Unit(id, start, end)
+    }
+
+    fn heartbeat(&mut self, id: u64) {
+        if let Some(unit) = self.outstanding.get_mut(&id) {
+            unit.deadline = Instant::now() + self.timeout;
+        }
+    }
+
+    fn progress(&mut self, id: u64, tried: u64) {
+        if let Some(unit) = self.outstanding.get_mut(&id) {
+            unit.deadline = Instant::now() + self.timeout;
+            unit.tried = tried.min(unit.end - unit.start);
+        }
+    }
+
+    fn complete(&mut self, id: u64) {
+        if let Some(unit) = self.outstanding.remove(&id) {
+            self.tried += unit.end - unit.start;
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.next >= self.end && self.outstanding.is_empty()
+    }
+
+    /// The candidates tried in completed units and reported by the workers of the others.
+    fn candidates(&self) -> u64 {
+        self.tried + self.outstanding.values().map(|unit| unit.tried).sum::<u64>()
+    }
+}
+
+pub struct Coordinator {
+    addr: SocketAddr,
+    units: Arc<Mutex<Units>>,
+    matches: Receiver<(usize, Password, Tripcode)>,
+}
+
+impl Coordinator {
+    /// Starts serving workers on `addr`, dividing the keyspace of `job` into units of
+    /// `unit_size` candidates which are handed out again after `timeout` without word from their
+    /// worker.
+    pub fn bind<A: ToSocketAddrs>(addr: A, job: &Job, unit_size: u64, timeout: Duration)
+        -> io::Result<Coordinator>
+    {
+        let mask = Mask::parse(&job.mask)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut config = format!("mask {}\n", hex(job.mask.as_bytes()));
+
+        for pattern in &job.patterns {
+            config += &format!("pattern {}\n", hex(pattern.as_bytes()));
+        }
+
+        if job.ignore_case {
+            config += "ignore-case\n";
+        }
+
+        let heartbeat = (timeout / 4).max(Duration::from_millis(1));
+        config += &format!("heartbeat {}\nready\n", heartbeat.as_millis());
+
+        let units = Units {
+            next: 0,
+            end: mask.size_hint().unwrap(),
+            size: unit_size.max(1),
+            timeout,
+            next_id: 0,
+            outstanding: HashMap::new(),
+            tried: 0,
+        };
+
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let units = Arc::new(Mutex::new(units));
+        let (tx, rx) = mpsc::channel();
+        let job = Arc::new(config);
+        let shared = units.clone();
+
+        thread::spawn(move || {
+            for (worker, stream) in listener.incoming().enumerate() {
+                if let Ok(stream) = stream {
+                    let (job, units, tx) = (job.clone(), shared.clone(), tx.clone());
+                    thread::spawn(move || serve(worker, stream, &job, &units, &tx));
+                }
+            }
+        });
+
+        Ok(Coordinator { addr, units, matches: rx })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The number of candidates tried so far, as reported by the workers, and the size of the
+    /// keyspace.
+    pub fn progress(&self) -> (u64, u64) {
+        let units = self.units.lock().unwrap();
+        (units.candidates(), units.end)
+    }
+
+    /// Hands out units until the whole keyspace has been tried or `stop` returns true, calling
+    /// `found` with the index of the worker's connection, the password and the tripcode of each
+    /// match. Returns the number of candidates tried, or the first error `found` returns.
+    pub fn run<F, S>(&self, mut found: F, mut stop: S) -> io::Result<u64>
+        where F: FnMut(usize, &Password, &Tripcode) -> io::Result<()>,
+              S: FnMut() -> bool
+    {
+        let mut seen = HashSet::new();
+
+        loop {
+            let finished = self.units.lock().unwrap().finished();
+
+            // Matches are sent before the unit they belong to is completed, so once every unit
+            // is complete, the matches still in the channel are the last ones.
+            let received = if finished {
+                self.matches.try_recv().ok()
+            } else {
+                self.matches.recv_timeout(POLL_INTERVAL).ok()
+            };
+
+            match received {
+                Some((worker, passwd, tripcode)) if seen.insert(tripcode) => {
+                    found(worker, &passwd, &tripcode)?;
+                }
+                Some(_) => {}
+                None if finished => break,
+                None => {}
+            }
+
+            // Checked even while matches keep arriving, so that a steady stream of them can't
+            // hold off a signal.
+            if stop() {
+                break;
+            }
+        }
+
+        let tried = self.units.lock().unwrap().candidates();
+        Ok(tried)
+    }
+}
+
+fn serve(
+    worker: usize,
+    stream: TcpStream,
+    job: &str,
+    units: &Mutex<Units>,
+    matches: &Sender<(usize, Password, Tripcode)>,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    writer.write_all(job.as_bytes())?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split(' ').collect();
+
+        match words.as_slice() {
+            ["request"] => {
+                let assignment = units.lock().unwrap().assign();
+
+                match assignment {
+                    Assignment::Unit(id, start, end) => {
+                        writeln!(writer, "unit {} {} {}", id, start, end)?
+                    }
+                    Assignment::Wait => writeln!(writer, "wait {}", POLL_INTERVAL.as_millis())?,
+                    Assignment::Done => writeln!(writer, "done")?,
+                }
+            }
+            ["progress", id, tried] => units.lock().unwrap().progress(number(id)?, number(tried)?),
+            ["match", id, passwd, tripcode] => {
+                units.lock().unwrap().heartbeat(number(id)?);
+                let passwd = Password::from(unhex(passwd)?);
+                let tripcode = tripcode.parse().map_err(|_| invalid(tripcode))?;
+
+                if trip::trip(*passwd.key()) != tripcode {
+                    return Err(invalid(&line));
+                }
+
+                let _ = matches.send((worker, passwd, tripcode));
+            }
+            ["complete", id] => units.lock().unwrap().complete(number(id)?),
+            _ => return Err(invalid(&line)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Connects to the coordinator at `addr` and tries the units it hands out using `procs`
+/// threads, until it has none left. Returns the number of candidates tried.
+pub fn work<A: ToSocketAddrs>(addr: A, procs: usize) -> io::Result<u64> {
+    let stream = TcpStream::connect(addr)?;
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    let mut next_line = || lines.next().unwrap_or_else(|| Err(invalid("end of stream")));
+    let mut mask = None;
+    let mut patterns = Vec::new();
+    let mut ignore_case = false;
+    let mut heartbeat = Duration::from_secs(1);
+
+    loop {
+        let line = next_line()?;
+        let words: Vec<&str> = line.split(' ').collect();
+
+        match words.as_slice() {
+            ["mask", spec] => mask = Some(string(spec)?),
+            ["pattern", pattern] => patterns.push(string(pattern)?),
+            ["ignore-case"] => ignore_case = true,
+            ["heartbeat", ms] => heartbeat = Duration::from_millis(number(ms)?.max(1)),
+            ["ready"] => break,
+            _ => return Err(invalid(&line)),
+        }
+    }
+
+    let mask = mask.ok_or_else(|| invalid("no mask"))?;
+    let mask = Mask::parse(&mask).map_err(|e| invalid(&e.to_string()))?;
+    let patterns = Arc::new(Patterns::new(patterns, ignore_case));
+    let mut tried = 0;
+
+    loop {
+        writeln!(writer, "request")?;
+        let line = next_line()?;
+        let words: Vec<&str> = line.split(' ').collect();
+
+        match words.as_slice() {
+            ["unit", id, start, end] => {
+                let (start, end) = (number(start)?, number(end)?);
+
+                if start > end || end > mask.size_hint().unwrap() {
+                    return Err(invalid(&line));
+                }
+
+                let unit = mask.clone().range(start, end);
+                try_unit(&mut writer, number(id)?, unit, procs, &patterns, heartbeat)?;
+                tried += end - start;
+            }
+            ["wait", ms] => thread::sleep(Duration::from_millis(number(ms)?)),
+            ["done"] => return Ok(tried),
+            _ => return Err(invalid(&line)),
+        }
+    }
+}
+
+fn try_unit<W: Write>(
+    writer: &mut W,
+    id: u64,
+    unit: Mask,
+    procs: usize,
+    patterns: &Arc<Patterns>,
+    heartbeat: Duration,
+) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let state = Arc::new(State::new(Limits::default(), procs));
+
+    let threads: Vec<_> = Box::new(unit).split(procs)
+        .into_iter()
+        .enumerate()
+        .map(|(worker, source)| {
+            let (tx, state, patterns) = (tx.clone(), state.clone(), patterns.clone());
+
+            thread::spawn(move || {
+                // Units are masks, which generate their candidates and never fail.
+                search::run(worker, source, &patterns, Profile::TWO_CHANNEL, &state, |found| {
+                    let _ = tx.send((Password::from_key(found.passwd), found.tripcode));
+                    Ok::<_, io::Error>(())
+                })
+            })
+        })
+        .collect();
+
+    drop(tx);
+
+    loop {
+        match rx.recv_timeout(heartbeat) {
+            Ok((passwd, tripcode)) => {
+                writeln!(writer, "match {} {} {}", id, hex(passwd.as_bytes()), tripcode)?;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                writeln!(writer, "progress {} {}", id, state.candidates())?;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    for thread in threads {
+        thread.join().unwrap_or_else(|_| Err(io::Error::other("a worker thread panicked")))?;
+    }
+
+    writeln!(writer, "complete {}", id)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid message: {}", message))
+}
+
+fn number(s: &str) -> io::Result<u64> {
+    s.parse().map_err(|_| invalid(s))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> io::Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return Err(invalid(s));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| invalid(s))
+        })
+        .collect()
+}
+
+fn string(s: &str) -> io::Result<String> {
+    String::from_utf8(unhex(s)?).map_err(|_| invalid(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback() {
+        let job = Job {
+            mask: "?d?d?d".to_string(),
+            patterns: vec!["A".to_string(), "zz".to_string()],
+            ignore_case: false,
+        };
+
+        let patterns = Patterns::new(job.patterns.clone(), job.ignore_case);
+        let mut mask: Box<dyn CandidateSource> = Box::new(Mask::parse(&job.mask).unwrap());
+        let mut batch = Vec::new();
+        while mask.fill(&mut batch, 1000).unwrap() {}
+
+        let mut expected: Vec<_> = batch.iter()
+            .map(|passwd| (Password::from_key(passwd), trip::trip(*passwd)))
+            .filter(|(_, tripcode)| patterns.find(tripcode.as_str()).is_some())
+            .collect();
+
+        expected.sort();
+
+        let timeout = Duration::from_millis(400);
+        let coordinator = Coordinator::bind("127.0.0.1:0", &job, 64, timeout).unwrap();
+        let addr = coordinator.local_addr();
+
+        // A worker that takes a unit and disappears without completing it.
+        {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+            while lines.next().unwrap().unwrap() != "ready" {}
+            writeln!(stream, "request").unwrap();
+            assert!(lines.next().unwrap().unwrap().starts_with("unit 0 0 64"));
+        }
+
+        let workers: Vec<_> = (0..2)
+            .map(|_| thread::spawn(move || work(addr, 2).unwrap()))
+            .collect();
+
+        let mut found = Vec::new();
+        let record = |_, passwd: &Password, tripcode: &Tripcode| {
+            found.push((passwd.clone(), *tripcode));
+            Ok(())
+        };
+
+        let tried = coordinator.run(record, || false).unwrap();
+
+        let worked: u64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+
+        found.sort();
+        assert_eq!(tried, 1000);
+        assert_eq!(worked, 1000);
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn stops_during_matches() {
+        use std::cell::Cell;
+
+        let job = Job { mask: "?d?d?d".to_string(), patterns: vec![], ignore_case: false };
+        let coordinator = Coordinator::bind("127.0.0.1:0", &job, 1000, Duration::from_secs(60))
+            .unwrap();
+
+        let mut stream = TcpStream::connect(coordinator.local_addr()).unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        while lines.next().unwrap().unwrap() != "ready" {}
+        writeln!(stream, "request").unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "unit 0 0 1000");
+
+        for i in 0..1000 {
+            let passwd = Password::from(format!("{:03}", i).as_str());
+            let tripcode = trip::trip(*passwd.key());
+            writeln!(stream, "match 0 {} {}", hex(passwd.as_bytes()), tripcode).unwrap();
+        }
+
+        let found = Cell::new(0);
+
+        let record = |_, _: &Password, _: &Tripcode| {
+            found.set(found.get() + 1);
+            Ok(())
+        };
+
+        coordinator.run(record, || found.get() >= 10).unwrap();
+        assert_eq!(found.get(), 10);
+    }
+
+    #[test]
+    fn forged_matches() {
+        let job = Job { mask: "?d".to_string(), patterns: vec![], ignore_case: false };
+        let coordinator = Coordinator::bind("127.0.0.1:0", &job, 5, Duration::from_secs(60))
+            .unwrap();
+
+        let mut stream = TcpStream::connect(coordinator.local_addr()).unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        while lines.next().unwrap().unwrap() != "ready" {}
+        writeln!(stream, "request").unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "unit 0 0 5");
+        writeln!(stream, "progress 0 3").unwrap();
+        writeln!(stream, "match 0 {} AAAAAAAAAA", hex(b"foofoofo")).unwrap();
+
+        // The coordinator hangs up on the forger.
+        assert!(lines.next().is_none_or(|line| line.is_err()));
+        assert_eq!(coordinator.progress(), (3, 10));
+
+        let mut found = 0;
+
+        let record = |_, _: &Password, _: &Tripcode| {
+            found += 1;
+            Ok(())
+        };
+
+        coordinator.run(record, || true).unwrap();
+        assert_eq!(found, 0);
+    }
+}
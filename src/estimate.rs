@@ -0,0 +1,106 @@
+use candidate::SAMPLES;
+use pattern::Patterns;
+use std::time::Duration;
+
+const TRIPCODE_LEN: usize = 10;
+
+/// The probability that a tripcode generated from a random password contains at least one of
+/// `patterns`.
+///
+/// This sums the probability of each pattern at each offset, which slightly overestimates the
+/// probability of patterns that can overlap each other.
+pub fn match_probability(patterns: &Patterns) -> f64 {
+    patterns.iter()
+        .map(|p| pattern_probability(p.as_bytes(), patterns.ignore_case()))
+        .sum::<f64>()
+        .min(1.0)
+}
+
+fn pattern_probability(pattern: &[u8], ignore_case: bool) -> f64 {
+    if pattern.len() > TRIPCODE_LEN {
+        return 0.0;
+    }
+
+    (0..TRIPCODE_LEN - pattern.len() + 1)
+        .map(|offset| {
+            pattern.iter()
+                .enumerate()
+                .map(|(i, &c)| {
+                    if ignore_case && c.is_ascii_alphabetic() {
+                        char_probability(offset + i, c.to_ascii_lowercase()) +
+                            char_probability(offset + i, c.to_ascii_uppercase())
+                    } else {
+                        char_probability(offset + i, c)
+                    }
+                })
+                .product::<f64>()
+        })
+        .sum()
+}
+
+fn char_probability(pos: usize, c: u8) -> f64 {
+    match SAMPLES.iter().position(|&s| s == c) {
+        // The last character only encodes four bits, so it is always one of every fourth
+        // character of the alphabet.
+        Some(i) if pos == TRIPCODE_LEN - 1 && i % 4 == 0 => 1.0 / 16.0,
+        Some(_) if pos == TRIPCODE_LEN - 1 => 0.0,
+        Some(_) => 1.0 / 64.0,
+        None => 0.0,
+    }
+}
+
+/// The expected time to try `candidates` candidates at `rate` candidates per second.
+pub fn time_for(candidates: f64, rate: f64) -> Option<Duration> {
+    let secs = candidates / rate;
+
+    if rate > 0.0 && secs.is_finite() && secs < u64::MAX as f64 {
+        Some(Duration::from_secs_f64(secs))
+    } else {
+        None
+    }
+}
+
+/// Formats a duration with its two most significant units, such as `3h05m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (d, h, m, s) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    if d > 0 {
+        format!("{}d{:02}h", d, h)
+    } else if h > 0 {
+        format!("{}h{:02}m", h, m)
+    } else if m > 0 {
+        format!("{}m{:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probability(patterns: &[&str], ignore_case: bool) -> f64 {
+        let patterns = patterns.iter().map(|p| p.to_string()).collect();
+        match_probability(&Patterns::new(patterns, ignore_case))
+    }
+
+    #[test]
+    fn probabilities() {
+        assert_eq!(probability(&[""], false), 1.0);
+        assert_eq!(probability(&["!"], false), 0.0);
+        assert_eq!(probability(&["aaaaaaaaaaa"], false), 0.0);
+        assert_eq!(probability(&["B"], false), 9.0 / 64.0);
+        assert_eq!(probability(&["E"], false), 9.0 / 64.0 + 1.0 / 16.0);
+        assert_eq!(probability(&["AA", "BB"], false), 20.0 / 4096.0);
+        assert_eq!(probability(&["b"], true), 18.0 / 64.0);
+        assert_eq!(probability(&["e"], true), 18.0 / 64.0 + 1.0 / 16.0);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(11105)), "3h05m");
+        assert_eq!(format_duration(Duration::from_secs(200000)), "2d07h");
+    }
+}
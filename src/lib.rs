@@ -0,0 +1,44 @@
+//! Finds tripcodes that contain patterns.
+//!
+//! Without the default `std` feature, only the tripcode function and what it needs are built,
+//! with `#![no_std]` and without allocating.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
+extern crate libc;
+
+#[cfg(feature = "std")]
+pub use password::Password;
+pub use trip::trip;
+pub use tripcode::Tripcode;
+
+#[cfg(feature = "std")]
+pub mod candidate;
+#[cfg(feature = "std")]
+pub mod distributed;
+#[cfg(feature = "std")]
+pub mod estimate;
+#[cfg(feature = "std")]
+pub mod output;
+#[cfg(feature = "std")]
+pub mod password;
+#[cfg(feature = "std")]
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod score;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod signal;
+#[cfg(feature = "std")]
+pub mod store;
+pub mod trip;
+pub mod tripcode;
+pub mod zeroize;
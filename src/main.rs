@@ -1,86 +1,579 @@
-#![feature(test)]
+extern crate trip;
 
-extern crate rand;
-extern crate test;
-
-use rand::{Rng, NewRng, XorShiftRng};
+use std::cell::Cell;
 use std::env;
-use std::io::{self, Read};
-use std::str;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
-use std::time::Instant;
-
-mod trip;
-
-const SAMPLES: [u8; 64] = *b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
-
-#[inline(always)]
-fn rand_passwd<R: Rng>(rng: &mut R) -> [u8; 8] {
-    let var: usize = rng.gen();
-
-    [
-	SAMPLES[ var        & 0x3f],
-	SAMPLES[(var >>  8) & 0x3f],
-	SAMPLES[(var >> 16) & 0x3f],
-	SAMPLES[(var >> 24) & 0x3f],
-	SAMPLES[(var >> 32) & 0x3f],
-	SAMPLES[(var >> 40) & 0x3f],
-	SAMPLES[(var >> 48) & 0x3f],
-	SAMPLES[(var >> 56) & 0x3f],
-    ]
+use std::time::{Duration, Instant, SystemTime};
+use trip::candidate::{
+    CandidateSource, Chain, Lines, Mask, ParseMaskError, Random, ReadError, EXHAUSTIVE,
+};
+use trip::distributed::{self, Coordinator, Job};
+use trip::output::{self, Format, Match};
+use trip::pattern::Patterns;
+use trip::progress::{self, Progress};
+use trip::score::{self, Leaderboard};
+use trip::search::{self, Limits, ParseArgError, State, Stop};
+use trip::store::Store;
+use trip::trip::{Compat, ParseCompatError, Profile, SelfTestError};
+use trip::{estimate, signal, Password, Tripcode};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long workers are given to finish their batches once the search stops.
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+const UNIT_SIZE: u64 = 1 << 30;
+
+const UNIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Options which only apply to searches run by this process, not to distributed ones.
+const LOCAL_OPTIONS: &[&str] = &[
+    "--duration",
+    "--max-candidates",
+    "--max-matches",
+    "--db",
+    "--best",
+    "--top",
+    "--part",
+    "--seed",
+];
+
+const USAGE: &str = "usage: trip [--exhaustive] [--mask mask]... [--wordlist file]... [--stdin]
+            [--duration duration] [--max-candidates count] [--max-matches count]
+            [--status interval] [--format plain|jsonl|csv] [--db file] [--ignore-case]
+            [--best] [--top count] [--part k/n] [--seed seed] [--raw-salt]
+            [--compat freesec|strict|ufc|ufc-unsigned|bsd] [pattern]...
+       trip --coordinator address [--exhaustive | --mask mask] [--unit-size count]
+            [--unit-timeout duration] [--status interval] [--format plain|jsonl|csv]
+            [--ignore-case] [pattern]...
+       trip --worker address
+       trip --db file --query pattern";
+
+/// Why trip exits unsuccessfully.
+#[derive(Debug)]
+enum Error {
+    /// Invalid arguments, reported along with the usage, as are invalid masks and values.
+    Usage(String),
+    Mask(ParseMaskError),
+    Arg(ParseArgError),
+    Compat(ParseCompatError),
+    /// An I/O error on the file, address or stream named by the string.
+    Io(String, io::Error),
+    SelfTest(SelfTestError),
+    /// A worker thread panicked, after its panic was reported.
+    Panic,
+}
+
+impl Error {
+    fn io(name: &str) -> impl Fn(io::Error) -> Error + '_ {
+	move |e| Error::Io(name.to_string(), e)
+    }
+
+    fn exit_code(&self) -> i32 {
+	match *self {
+	    Error::Usage(_) | Error::Mask(_) | Error::Arg(_) | Error::Compat(_) => 2,
+	    Error::SelfTest(_) => 3,
+	    Error::Io(..) | Error::Panic => 1,
+	}
+    }
+}
+
+// Only `search::run` reports I/O errors without naming their source: those reading candidates,
+// which are `ReadError`s naming it themselves.
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+	match e.downcast::<ReadError>() {
+	    Ok(e) => Error::Io(e.name, e.error),
+	    Err(e) => Error::Io("reading candidates".to_string(), e),
+	}
+    }
+}
+
+impl From<ParseMaskError> for Error {
+    fn from(e: ParseMaskError) -> Error {
+	Error::Mask(e)
+    }
+}
+
+impl From<ParseArgError> for Error {
+    fn from(e: ParseArgError) -> Error {
+	Error::Arg(e)
+    }
+}
+
+impl From<ParseCompatError> for Error {
+    fn from(e: ParseCompatError) -> Error {
+	Error::Compat(e)
+    }
+}
+
+impl From<SelfTestError> for Error {
+    fn from(e: SelfTestError) -> Error {
+	Error::SelfTest(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match *self {
+	    Error::Usage(ref e) => write!(f, "{}\n{}", e, USAGE),
+	    Error::Mask(ref e) => write!(f, "{}\n{}", e, USAGE),
+	    Error::Arg(ref e) => write!(f, "{}\n{}", e, USAGE),
+	    Error::Compat(ref e) => write!(f, "{}\n{}", e, USAGE),
+	    Error::Io(ref name, ref e) => write!(f, "{}: {}", name, e),
+	    Error::SelfTest(ref e) => write!(f, "self-test failed, refusing to search: {}", e),
+	    Error::Panic => f.write_str("a worker thread panicked"),
+	}
+    }
+}
+
+fn usage<T>(err: &str) -> Result<T, Error> {
+    Err(Error::Usage(err.to_string()))
+}
+
+fn stdout_error(e: io::Error) -> Error {
+    Error::Io("standard output".to_string(), e)
+}
+
+fn coordinate(
+    addr: &str,
+    job: Job,
+    unit_size: u64,
+    timeout: Duration,
+    format: Format,
+    status: Option<Duration>,
+) -> Result<(), Error> {
+    let coordinator = Coordinator::bind(addr, &job, unit_size, timeout).map_err(Error::io(addr))?;
+
+    eprintln!("Listening for workers on {}", coordinator.local_addr());
+
+    let patterns = Patterns::new(job.patterns, job.ignore_case);
+    let now = Instant::now();
+    let matches = Cell::new(0);
+    let mut next_status = status.map(|interval| now + interval);
+    signal::install();
+
+    if let Some(header) = format.header() {
+	writeln!(io::stdout(), "{}", header).map_err(stdout_error)?;
+    }
+
+    let found = |worker, passwd: &Password, tripcode: &Tripcode| {
+	if let Some((pattern, offset)) = patterns.find(tripcode.as_str()) {
+	    let m = Match {
+		passwd,
+		tripcode,
+		pattern,
+		offset,
+		timestamp: SystemTime::now(),
+		worker,
+		score: score::score(&patterns, tripcode.as_str()),
+	    };
+
+	    format.write(&mut io::stdout(), &m)?;
+	    matches.set(matches.get() + 1);
+	}
+
+	Ok(())
+    };
+
+    let stop = || {
+	if let (Some(next), Some(interval)) = (next_status, status) {
+	    if Instant::now() >= next {
+		let (tried, size) = coordinator.progress();
+		let rate = progress::rate(tried, now.elapsed());
+		let mut line = format!(
+		    "{} of {} candidates, average {}/s, {} matches",
+		    tried,
+		    size,
+		    rate,
+		    matches.get()
+		);
+
+		if let Some(eta) = estimate::time_for((size - tried) as f64, rate as f64) {
+		    line += &format!(", done in ~{}", estimate::format_duration(eta));
+		}
+
+		eprintln!("{}", line);
+		next_status = Some(next + interval);
+	    }
+	}
+
+	signal::received()
+    };
+
+    // Only writing matches fails.
+    let count = coordinator.run(found, stop).map_err(stdout_error)?;
+
+    eprintln!(
+	"Processed {} tripcodes ({}/second) and found {} matches",
+	count,
+	progress::rate(count, now.elapsed()),
+	matches.get()
+    );
+
+    Ok(())
+}
+
+fn work(addr: &str, procs: usize) -> Result<(), Error> {
+    let count = distributed::work(addr, procs).map_err(Error::io(addr))?;
+    eprintln!("Processed {} tripcodes", count);
+    Ok(())
 }
 
 fn main() {
+    if let Err(e) = run() {
+	eprintln!("trip: {}", e);
+	process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), Error> {
     let procs = env::var("NUMBER_OF_PROCESSORS")
 	.ok()
 	.and_then(|v| v.parse().ok())
-	.unwrap_or(1);
+	.unwrap_or(1)
+	.max(1);
+
+    let mut sources: Vec<Box<dyn CandidateSource>> = Vec::new();
+    let mut masks = Vec::new();
+    let mut patterns = Vec::new();
+    let mut streaming = false;
+    let mut limits = Limits::default();
+    let mut format = Format::Plain;
+    let mut db = None;
+    let mut query = None;
+    let mut ignore_case = false;
+    let mut best_only = false;
+    let mut top = 0;
+    let mut coordinator = None;
+    let mut worker = None;
+    let mut unit_size = UNIT_SIZE;
+    let mut unit_timeout = UNIT_TIMEOUT;
+    let mut part = None;
+    let mut seed = None;
+    let mut profile = Profile::TWO_CHANNEL;
+    let mut status = if progress::stderr_is_terminal() { Some(STATUS_INTERVAL) } else { None };
+    let mut options = Vec::new();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+	if arg.starts_with("--") {
+	    options.push(arg.clone());
+	}
 
+	let mut value = || {
+	    args.next().ok_or_else(|| Error::Usage(format!("{} requires an argument", arg)))
+	};
 
-    let abort = Arc::new(AtomicBool::new(false));
+	match arg.as_str() {
+	    "--exhaustive" => {
+		sources.push(Box::new(Mask::exhaustive()));
+		masks.push(EXHAUSTIVE.to_string());
+	    }
+	    "--mask" => {
+		let mask = value()?;
+		sources.push(Box::new(Mask::parse(&mask)?));
+		masks.push(mask);
+	    }
+	    "--wordlist" => {
+		let path = value()?;
+		sources.push(Box::new(Lines::open(&path).map_err(Error::io(&path))?));
+	    }
+	    "--stdin" => {
+		if !streaming {
+		    sources.push(Box::new(Lines::stdin()));
+		}
+
+		streaming = true;
+	    }
+	    "--duration" => {
+		limits.duration = Some(search::parse_duration(&value()?)?);
+	    }
+	    "--max-candidates" => {
+		limits.candidates = Some(search::parse_count(&value()?)?);
+	    }
+	    "--max-matches" => {
+		limits.matches = Some(search::parse_count(&value()?)?);
+	    }
+	    "--format" => format = value()?.parse()?,
+	    "--db" => db = Some(value()?),
+	    "--query" => query = Some(value()?),
+	    "--ignore-case" => ignore_case = true,
+	    "--best" => best_only = true,
+	    "--top" => top = search::parse_count(&value()?)? as usize,
+	    "--part" => part = Some(search::parse_part(&value()?)?),
+	    "--seed" => {
+		let value = value()?;
+		seed = Some(value.parse().map_err(|_| ParseArgError::Invalid("seed", value))?);
+	    }
+	    "--raw-salt" => profile.map_salt = false,
+	    "--compat" => profile.compat = value()?.parse::<Compat>()?,
+	    "--coordinator" => coordinator = Some(value()?),
+	    "--worker" => worker = Some(value()?),
+	    "--unit-size" => {
+		unit_size = search::parse_count(&value()?)?;
+	    }
+	    "--unit-timeout" => {
+		unit_timeout = search::parse_duration(&value()?)?;
+
+		if unit_timeout == Duration::from_secs(0) {
+		    return usage("--unit-timeout must be positive");
+		}
+	    }
+	    "--status" => {
+		status = Some(search::parse_duration(&value()?)?)
+		    .filter(|interval| *interval > Duration::from_secs(0));
+	    }
+	    _ if arg.starts_with("--") => return usage(&format!("unknown option {}", arg)),
+	    _ => patterns.push(arg.clone()),
+	}
+    }
+
+    if let Some(pattern) = query {
+	let store = match db {
+	    Some(path) => Store::open(&path).map_err(Error::io(&path))?,
+	    None => return usage("--query requires --db"),
+	};
+	let stdout = io::stdout();
+	let mut stdout = stdout.lock();
+
+	for (tripcode, passwd) in store.query(&pattern) {
+	    writeln!(stdout, "#{} => {}", passwd, tripcode).map_err(stdout_error)?;
+	}
+
+	return Ok(());
+    }
+
+    trip::trip::self_test()?;
+
+    if let Some(addr) = worker {
+	// The coordinator says what to search for.
+	if options.len() > 1 || !patterns.is_empty() {
+	    return usage("--worker takes no other options or patterns");
+	}
+
+	return work(&addr, procs);
+    }
+
+    if let Some(addr) = coordinator {
+	if let Some(option) = options.iter().find(|o| LOCAL_OPTIONS.contains(&o.as_str())) {
+	    return usage(&format!("--coordinator doesn't support {}", option));
+	}
+
+	if sources.len() != masks.len() || masks.len() > 1 {
+	    return usage("--coordinator searches at most one mask");
+	}
+
+	if profile != Profile::TWO_CHANNEL {
+	    return usage("--coordinator only searches with 2channel's rules");
+	}
+
+	let job = Job {
+	    mask: masks.pop().unwrap_or_else(|| EXHAUSTIVE.to_string()),
+	    patterns,
+	    ignore_case,
+	};
+
+	return coordinate(&addr, job, unit_size, unit_timeout, format, status);
+    }
+
+    // --part may follow the masks it splits, so they are parsed again once every option is read.
+    if let Some((k, n)) = part {
+	if sources.is_empty() || sources.len() != masks.len() {
+	    return usage("--part only splits --mask and --exhaustive searches");
+	}
+
+	sources = masks
+	    .iter()
+	    .map(|mask| {
+		let mask = Mask::parse(mask)?.part(k, n);
+		Ok(Box::new(mask) as Box<dyn CandidateSource>)
+	    })
+	    .collect::<Result<_, Error>>()?;
+    }
+
+    if seed.is_some() && !sources.is_empty() {
+	return usage("--seed only applies to random searches");
+    }
+
+    let source: Box<dyn CandidateSource> = if sources.is_empty() {
+	Box::new(seed.map_or_else(Random::new, Random::seeded))
+    } else {
+	Box::new(Chain::new(sources))
+    };
+
+    signal::install();
+
+    let size = source.size_hint();
+    let patterns = Patterns::new(patterns, ignore_case);
+    let probability = estimate::match_probability(&patterns);
+    let patterns = Arc::new(patterns);
+    // Opened only once the options are known to be valid, so that invalid ones don't create it.
+    let db = match db {
+	Some(path) => Some(Arc::new(Mutex::new(Store::open(&path).map_err(Error::io(&path))?))),
+	None => None,
+    };
+
+    let board = if best_only || top > 0 {
+	Some(Arc::new(Mutex::new(Leaderboard::new(top))))
+    } else {
+	None
+    };
+    let state = Arc::new(State::new(limits, procs));
+    let (done_tx, done_rx) = mpsc::channel::<()>();
     let now = Instant::now();
+    let mut progress = Progress::new(now, procs, probability, size);
+    let mut next_status = status.map(|interval| now + interval);
+
+    if let Some(header) = format.header() {
+	writeln!(io::stdout(), "{}", header).map_err(stdout_error)?;
+    }
+
+    let threads: Vec<_> = source.split(procs)
+	.into_iter()
+	.enumerate()
+	.map(|(worker, source)| {
+	    let state = state.clone();
+	    let patterns = patterns.clone();
+	    let db = db.clone();
+	    let board = board.clone();
+	    let done = done_tx.clone();
 
-    let threads: Vec<_> = (0..procs)
-	.map(|_| abort.clone())
-	.map(|abort| {
 	    thread::spawn(move || {
-		let mut rng = XorShiftRng::new();
-		let mut count = 0;
+		// Dropped when the worker exits, even if it panics.
+		let _done = done;
+
+		search::run(worker, source, &patterns, profile, &state, |found| {
+		    let passwd = Password::from_key(found.passwd);
+		    let tripcode = found.tripcode;
+		    let timestamp = SystemTime::now();
 
-		while !abort.load(Ordering::Relaxed) {
-		    let passwd = rand_passwd(&mut rng);
-		    let tripcode = trip::trip(passwd);
-		    let tripcode_str = str::from_utf8(&tripcode).unwrap();
+		    let mut db = db.as_ref().map(|db| db.lock().unwrap());
 
-		    if env::args().find(|a| tripcode_str.contains(a.as_str())).is_some() {
-		        println!("#{} => {}", str::from_utf8(&passwd).unwrap(), tripcode_str);
+		    if db.as_ref().is_some_and(|db| db.contains(&tripcode)) {
+			return Ok(());
 		    }
 
-		    count += 1;
-		}
+		    let score = score::score(&patterns, tripcode.as_str());
+
+		    let best = board.as_ref().is_none_or(|board| {
+			board.lock().unwrap().offer(score, &tripcode, &passwd)
+		    });
+
+		    if (best_only && !best) || !state.record_match() {
+			return Ok(());
+		    }
+
+		    if let Some(ref mut db) = db {
+			let time = output::rfc3339(timestamp);
+			db.insert(&tripcode, &passwd, &time).map_err(Error::io("database"))?;
+		    }
 
-		count
+		    let m = Match {
+			passwd: &passwd,
+			tripcode: &tripcode,
+			pattern: found.pattern,
+			offset: found.offset,
+			timestamp,
+			worker,
+			score,
+		    };
+
+		    let stdout = io::stdout();
+		    format.write(&mut stdout.lock(), &m).map_err(stdout_error)
+		})
 	    })
 	})
 	.collect();
 
-    io::stdin().bytes().next().unwrap().unwrap();
-    abort.store(true, Ordering::Relaxed);
-    let count: u64 = threads.into_iter().map(|t| t.join().unwrap()).sum();
-    let count_per_second = count / now.elapsed().as_secs();
-    println!("Processed {} tripcodes ({}/second)", count, count_per_second);
-}
+    drop(done_tx);
+
+    // Candidates read from stdin end with EOF rather than with the Enter key. Without a terminal,
+    // stdin is at EOF immediately and only a signal stops the search.
+    if !streaming {
+	let state = state.clone();
+
+	thread::spawn(move || {
+	    if let Ok(1) = io::stdin().read(&mut [0]) {
+		state.stop(Stop::Enter);
+	    }
+	});
+    }
+
+    let mut stopped = None;
+    let mut abandoned = false;
+
+    while let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(POLL_INTERVAL) {
+	if signal::received() {
+	    state.stop(Stop::Signal);
+	}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test::Bencher;
+	// A worker waiting for candidates from stdin only sees the search stop once the next
+	// line arrives, so it is left behind rather than waited for.
+	if state.aborted() && stopped.get_or_insert_with(Instant::now).elapsed() >= STOP_TIMEOUT {
+	    abandoned = true;
+	    break;
+	}
 
-    #[bench]
-    fn bench_rand_passwd(b: &mut Bencher) {
-	let mut rng = XorShiftRng::new();
-	b.iter(|| rand_passwd(&mut rng));
+	state.check_duration();
+
+	if let (Some(next), Some(interval)) = (next_status, status) {
+	    if Instant::now() >= next {
+		eprintln!("{}", progress.status(&state));
+		next_status = Some(next + interval);
+	    }
+	}
+    }
+
+    let mut result = Ok(());
+
+    for thread in threads {
+	if abandoned && !thread.is_finished() {
+	    continue;
+	}
+
+	let joined = thread.join().unwrap_or(Err(Error::Panic));
+
+	if result.is_ok() {
+	    result = joined;
+	}
     }
+
+    result?;
+
+    io::stdout().flush().map_err(stdout_error)?;
+    let count = state.candidates();
+    let count_per_second = progress::rate(count, now.elapsed());
+
+    let mut summary = format!(
+	"Processed {} tripcodes ({}/second) and found {} matches\nStopped because {}",
+	count,
+	count_per_second,
+	state.matches(),
+	state.reason()
+    );
+
+    if let Some(board) = board.filter(|_| top > 0) {
+	summary += "\nBest matches:";
+
+	for entry in board.lock().unwrap().entries() {
+	    summary += &format!("\n{:5} #{} => {}", entry.score, entry.passwd, entry.tripcode);
+	}
+    }
+
+    // Keep stdout limited to matches so that it can be piped into other tools.
+    if streaming || format != Format::Plain {
+	eprintln!("{}", summary);
+    } else {
+	writeln!(io::stdout(), "{}", summary).map_err(stdout_error)?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,255 @@
+use password::Password;
+use search::ParseArgError;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::str::{self, FromStr};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tripcode::Tripcode;
+use zeroize::Zeroizing;
+
+/// How matches are written to stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `#password => tripcode`, as typed into a name field, with unprintable characters in the
+    /// password escaped.
+    Plain,
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma-separated values with a header line.
+    Csv,
+}
+
+/// How a password is written in a record: as is if it is UTF-8, and otherwise escaped as it is
+/// printed, along with its bytes in hex.
+///
+/// Strings holding passwords are allocated at their largest possible size up front, so that
+/// they are never moved, leaving copies behind, before they are zeroed.
+fn password_fields(passwd: &Password) -> (Zeroizing<String>, Option<Zeroizing<String>>) {
+    let bytes = passwd.as_bytes();
+
+    match str::from_utf8(bytes) {
+        Ok(s) => (Zeroizing::new(s.to_string()), None),
+        Err(_) => {
+            // Each byte is escaped as at most `\xNN`.
+            let mut escaped = Zeroizing::new(String::with_capacity(bytes.len() * 4));
+            let mut hex = Zeroizing::new(String::with_capacity(bytes.len() * 2));
+            write!(escaped, "{}", passwd).unwrap();
+
+            for b in bytes {
+                write!(hex, "{:02x}", b).unwrap();
+            }
+
+            (escaped, Some(hex))
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseArgError;
+
+    fn from_str(s: &str) -> Result<Format, ParseArgError> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(ParseArgError::Unknown("format", s.to_string())),
+        }
+    }
+}
+
+/// A tripcode found by a search.
+pub struct Match<'a> {
+    pub passwd: &'a Password,
+    pub tripcode: &'a Tripcode,
+    /// The first pattern found in the tripcode.
+    pub pattern: &'a str,
+    /// The byte offset of the pattern in the tripcode.
+    pub offset: usize,
+    pub timestamp: SystemTime,
+    /// The index of the worker thread which found the match.
+    pub worker: usize,
+    pub score: u32,
+}
+
+impl Format {
+    /// The line to write before any matches.
+    pub fn header(self) -> Option<&'static str> {
+        match self {
+            Format::Csv => {
+                Some("password,tripcode,pattern,offset,timestamp,worker,score,password_hex")
+            }
+            _ => None,
+        }
+    }
+
+    pub fn write<W: Write>(self, w: &mut W, m: &Match) -> io::Result<()> {
+        let (passwd, passwd_hex) = password_fields(m.passwd);
+
+        match self {
+            Format::Plain => writeln!(w, "#{} => {}", m.passwd, m.tripcode),
+            Format::JsonLines => {
+                write!(w, "{{\"password\":{},", *json_string(&passwd))?;
+
+                if let Some(hex) = passwd_hex {
+                    write!(w, "\"password_hex\":\"{}\",", *hex)?;
+                }
+
+                writeln!(
+                    w,
+                    "\"tripcode\":{},\"pattern\":{},\"offset\":{},\"timestamp\":\"{}\",\
+                     \"worker\":{},\"score\":{}}}",
+                    *json_string(m.tripcode.as_str()),
+                    *json_string(m.pattern),
+                    m.offset,
+                    rfc3339(m.timestamp),
+                    m.worker,
+                    m.score
+                )
+            }
+            Format::Csv => {
+                writeln!(
+                    w,
+                    "{},{},{},{},{},{},{},{}",
+                    *csv_field(&passwd),
+                    *csv_field(m.tripcode.as_str()),
+                    *csv_field(m.pattern),
+                    m.offset,
+                    rfc3339(m.timestamp),
+                    m.worker,
+                    m.score,
+                    passwd_hex.as_ref().map_or("", |hex| hex.as_str())
+                )
+            }
+        }
+    }
+}
+
+fn json_string(s: &str) -> Zeroizing<String> {
+    // Each byte is escaped as at most `\u00NN`.
+    let mut json = Zeroizing::new(String::with_capacity(s.len() * 6 + 2));
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn csv_field(s: &str) -> Zeroizing<String> {
+    if !s.contains([',', '"', '\n', '\r']) {
+        return Zeroizing::new(s.to_string());
+    }
+
+    // Each quote is doubled.
+    let mut field = Zeroizing::new(String::with_capacity(s.len() * 2 + 2));
+    field.push('"');
+
+    for c in s.chars() {
+        if c == '"' {
+            field.push('"');
+        }
+
+        field.push(c);
+    }
+
+    field.push('"');
+    field
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with millisecond precision.
+pub fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let days = secs / 86400;
+
+    // Howard Hinnant's civil_from_days, restricted to dates after the epoch.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record(format: Format, passwd: &[u8]) -> String {
+        let m = Match {
+            passwd: &Password::from(passwd),
+            tripcode: &"vctoKCJ4Fk".parse().unwrap(),
+            pattern: "KCJ",
+            offset: 4,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+            worker: 3,
+            score: 97,
+        };
+
+        let mut out = Vec::new();
+        format.write(&mut out, &m).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(record(Format::Plain, b"foofoofo"), "#foofoofo => vctoKCJ4Fk\n");
+        assert_eq!(record(Format::Plain, b"a\n\xff"), "#a\\x0a\\xff => vctoKCJ4Fk\n");
+
+        assert_eq!(
+            record(Format::JsonLines, b"a\"b\\c\x01"),
+            "{\"password\":\"a\\\"b\\\\c\\u0001\",\"tripcode\":\"vctoKCJ4Fk\",\"pattern\":\"KCJ\",\
+             \"offset\":4,\"timestamp\":\"2023-11-14T22:13:20.250Z\",\"worker\":3,\"score\":97}\n"
+        );
+
+        assert_eq!(
+            record(Format::JsonLines, b"\x82\xa0\x82\xa2"),
+            "{\"password\":\"\\\\x82\\\\xa0\\\\x82\\\\xa2\",\"password_hex\":\"82a082a2\",\
+             \"tripcode\":\"vctoKCJ4Fk\",\"pattern\":\"KCJ\",\"offset\":4,\
+             \"timestamp\":\"2023-11-14T22:13:20.250Z\",\"worker\":3,\"score\":97}\n"
+        );
+
+        assert_eq!(
+            record(Format::Csv, b"a,\"b"),
+            "\"a,\"\"b\",vctoKCJ4Fk,KCJ,4,2023-11-14T22:13:20.250Z,3,97,\n"
+        );
+
+        assert_eq!(
+            record(Format::Csv, b"\x82\xa0\x82\xa2"),
+            "\\x82\\xa0\\x82\\xa2,vctoKCJ4Fk,KCJ,4,2023-11-14T22:13:20.250Z,3,97,82a082a2\n"
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        let time = |secs| rfc3339(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(time(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(time(951782400), "2000-02-29T00:00:00.000Z");
+        assert_eq!(time(4107542399), "2100-02-28T23:59:59.000Z");
+    }
+}
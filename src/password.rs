@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str;
+use zeroize::Zeroizing;
+
+/// A password: any bytes, of which crypt(3) uses only the first eight. Zeroed when dropped.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Password(Zeroizing<Vec<u8>>);
+
+impl Password {
+    /// The password a candidate key stands for: its bytes up to the first NUL.
+    pub fn from_key(key: &[u8; 8]) -> Password {
+        let len = key.iter().position(|&c| c == 0).unwrap_or(key.len());
+        Password::from(&key[..len])
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The key crypt(3) is given: the first eight bytes, padded with NUL bytes.
+    pub fn key(&self) -> Zeroizing<[u8; 8]> {
+        let mut key = Zeroizing::new([0u8; 8]);
+        let len = self.0.len().min(8);
+        key[..len].copy_from_slice(&self.0[..len]);
+        key
+    }
+}
+
+impl From<Vec<u8>> for Password {
+    fn from(bytes: Vec<u8>) -> Password {
+        Password(Zeroizing::new(bytes))
+    }
+}
+
+impl From<&[u8]> for Password {
+    fn from(bytes: &[u8]) -> Password {
+        Password::from(bytes.to_vec())
+    }
+}
+
+impl From<&str> for Password {
+    fn from(s: &str) -> Password {
+        Password::from(s.as_bytes())
+    }
+}
+
+/// Writes the password as text that is safe to print: UTF-8 is written as is, except for
+/// backslashes and control characters, which are escaped like the bytes that aren't UTF-8, as
+/// `\\` or `\xNN`.
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = &self.0[..];
+
+        loop {
+            let (valid, rest) = match str::from_utf8(bytes) {
+                Ok(s) => (s, &[][..]),
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    (str::from_utf8(valid).unwrap(), rest)
+                }
+            };
+
+            for c in valid.chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    c if c.is_control() => {
+                        let mut buf = [0; 4];
+
+                        for b in c.encode_utf8(&mut buf).bytes() {
+                            write!(f, "\\x{:02x}", b)?;
+                        }
+                    }
+                    c => write!(f, "{}", c)?,
+                }
+            }
+
+            match rest.split_first() {
+                Some((&invalid, rest)) => {
+                    write!(f, "\\x{:02x}", invalid)?;
+                    bytes = rest;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Password(\"{}\")", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Password::from("foofoofo").to_string(), "foofoofo");
+        assert_eq!(Password::from("あ").to_string(), "あ");
+        assert_eq!(Password::from(&b"a\\b\x01\n\x7f"[..]).to_string(), "a\\\\b\\x01\\x0a\\x7f");
+        assert_eq!(Password::from(&b"\x82\xa0x\xe3\x81"[..]).to_string(), "\\x82\\xa0x\\xe3\\x81");
+        assert_eq!(Password::from("\u{85}").to_string(), "\\xc2\\x85");
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(Password::from_key(b"ab\0\0\0\0\0\0").as_bytes(), b"ab");
+        assert_eq!(*Password::from("tripcodes").key(), *b"tripcode");
+        assert_eq!(*Password::from("a").key(), *b"a\0\0\0\0\0\0\0");
+    }
+}
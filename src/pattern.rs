@@ -0,0 +1,70 @@
+/// The patterns searched for in tripcodes.
+pub struct Patterns {
+    patterns: Vec<String>,
+    /// The patterns in lowercase, if they match tripcodes regardless of case.
+    lowercase: Option<Vec<String>>,
+}
+
+impl Patterns {
+    pub fn new(patterns: Vec<String>, ignore_case: bool) -> Patterns {
+        let lowercase = if ignore_case {
+            Some(patterns.iter().map(|p| p.to_ascii_lowercase()).collect())
+        } else {
+            None
+        };
+
+        Patterns { patterns, lowercase }
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.lowercase.is_some()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, String> {
+        self.patterns.iter()
+    }
+
+    /// The first pattern found in `tripcode`, and the offset of its first occurrence.
+    pub fn find(&self, tripcode: &str) -> Option<(&str, usize)> {
+        match self.lowercase {
+            Some(ref lowercase) => {
+                found(lowercase, &self.patterns, &tripcode.to_ascii_lowercase()).next()
+            }
+            None => found(&self.patterns, &self.patterns, tripcode).next(),
+        }
+    }
+
+    /// Each pattern found in `tripcode`, and the offset of its first occurrence.
+    pub fn find_all(&self, tripcode: &str) -> Vec<(&str, usize)> {
+        match self.lowercase {
+            Some(ref lowercase) => {
+                found(lowercase, &self.patterns, &tripcode.to_ascii_lowercase()).collect()
+            }
+            None => found(&self.patterns, &self.patterns, tripcode).collect(),
+        }
+    }
+}
+
+fn found<'a, 'b>(needles: &'b [String], patterns: &'a [String], haystack: &'b str)
+    -> impl Iterator<Item = (&'a str, usize)> + 'b
+    where 'a: 'b
+{
+    needles.iter().zip(patterns).filter_map(move |(needle, pattern)| {
+        haystack.find(needle.as_str()).map(|offset| (pattern.as_str(), offset))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find() {
+        let exact = Patterns::new(vec!["Admin".to_string(), "jS".to_string()], false);
+        assert_eq!(exact.find("xxadminxjS"), Some(("jS", 8)));
+        assert_eq!(exact.find("xxadminxxx"), None);
+
+        let any = Patterns::new(vec!["Admin".to_string(), "jS".to_string()], true);
+        assert_eq!(any.find_all("xxadminxJs"), vec![("Admin", 2), ("jS", 8)]);
+    }
+}
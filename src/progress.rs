@@ -0,0 +1,108 @@
+use estimate;
+use search::State;
+use std::time::{Duration, Instant};
+
+/// Formats periodic status lines from the counters of a running search.
+pub struct Progress {
+    start: Instant,
+    last: Instant,
+    last_counts: Vec<u64>,
+    probability: f64,
+    size: Option<u64>,
+}
+
+impl Progress {
+    /// `probability` is the chance that a candidate matches and `size` is the number of
+    /// candidates the source will generate, if known.
+    pub fn new(start: Instant, workers: usize, probability: f64, size: Option<u64>) -> Progress {
+        Progress {
+            start,
+            last: start,
+            last_counts: vec![0; workers],
+            probability,
+            size,
+        }
+    }
+
+    pub fn status(&mut self, state: &State) -> String {
+        let now = Instant::now();
+        let counts = state.worker_candidates();
+        let interval = (now - self.last).as_secs_f64();
+        let elapsed = (now - self.start).as_secs_f64();
+
+        let rates: Vec<f64> = counts.iter()
+            .zip(&self.last_counts)
+            .map(|(&count, &last)| (count - last) as f64 / interval)
+            .collect();
+
+        let count: u64 = counts.iter().sum();
+        let rate: f64 = rates.iter().sum();
+        let slowest = rates.iter().cloned().fold(f64::INFINITY, f64::min);
+        let fastest = rates.iter().cloned().fold(0.0, f64::max);
+        let average = count as f64 / elapsed;
+        let matches = state.matches();
+
+        let mut status = format!(
+            "{} candidates, {:.0}/s ({:.0}-{:.0}/s per thread), average {:.0}/s ({:.0}/s per \
+             thread), {} matches",
+            count,
+            rate,
+            slowest,
+            fastest,
+            average,
+            average / counts.len() as f64,
+            matches
+        );
+
+        if let Some(eta) = estimate::time_for(1.0 / self.probability, average) {
+            status += &format!(", next match in ~{}", estimate::format_duration(eta));
+        }
+
+        let limits = state.limits();
+
+        let remaining = [
+            limits.candidates.map(|max| max.saturating_sub(count) as f64),
+            self.size.map(|size| size.saturating_sub(count) as f64),
+            limits.matches.map(|max| max.saturating_sub(matches) as f64 / self.probability),
+        ];
+
+        let remaining = remaining.iter().filter_map(|&r| r).fold(f64::INFINITY, f64::min);
+        let by_count = estimate::time_for(remaining, average);
+
+        let by_time = limits.duration.map(|d| d.checked_sub(now - self.start).unwrap_or_default());
+
+        let eta = match (by_count, by_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        if let Some(eta) = eta {
+            status += &format!(", done in ~{}", estimate::format_duration(eta));
+        }
+
+        self.last = now;
+        self.last_counts = counts;
+        status
+    }
+}
+
+/// The number of candidates tried per second over `elapsed`, or zero if no time has elapsed.
+pub fn rate(count: u64, elapsed: Duration) -> u64 {
+    let secs = elapsed.as_secs_f64();
+
+    if secs > 0.0 {
+        (count as f64 / secs) as u64
+    } else {
+        0
+    }
+}
+
+#[cfg(unix)]
+pub fn stderr_is_terminal() -> bool {
+    unsafe { ::libc::isatty(2) == 1 }
+}
+
+#[cfg(not(unix))]
+pub fn stderr_is_terminal() -> bool {
+    false
+}
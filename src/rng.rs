@@ -0,0 +1,74 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of uniformly random 64-bit words.
+pub trait Generator: Send {
+    fn next_u64(&mut self) -> u64;
+}
+
+/// A counter-based generator: the `n`th word is a hash of `n` and the key alone, so the output
+/// is the same on every platform.
+///
+/// The hash is the SplitMix64 finalizer, which is fast and passes BigCrush. It is not suitable for
+/// anything that needs to be unpredictable.
+pub struct Counter {
+    key: u64,
+    counter: u64,
+}
+
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Counter {
+    pub fn new(key: u64) -> Counter {
+        Counter { key, counter: 0 }
+    }
+
+    /// A generator with a key drawn from the randomness std seeds its hash maps with, which is
+    /// different for every call.
+    pub fn from_entropy() -> Counter {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut hasher = RandomState::new().build_hasher();
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        hasher.write_u64(since_epoch.as_secs());
+        hasher.write_u32(since_epoch.subsec_nanos());
+        hasher.write_usize(CALLS.fetch_add(1, Ordering::Relaxed));
+        Counter::new(hasher.finish())
+    }
+
+    /// The `index`th of a family of independent generators derived from `seed`.
+    pub fn stream(seed: u64, index: u64) -> Counter {
+        Counter::new(mix(seed ^ mix(index.wrapping_add(1).wrapping_mul(GAMMA))))
+    }
+}
+
+impl Generator for Counter {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.counter = self.counter.wrapping_add(1);
+        mix(self.key.wrapping_add(self.counter.wrapping_mul(GAMMA)))
+    }
+}
+
+#[inline]
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitmix64() {
+        // The first outputs of the reference SplitMix64 seeded with 1234567.
+        let mut rng = Counter::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+        assert_eq!(rng.next_u64(), 9817491932198370423);
+    }
+}
@@ -0,0 +1,140 @@
+use password::Password;
+use pattern::Patterns;
+use tripcode::Tripcode;
+
+/// Points for each character of a pattern found in a tripcode.
+const LENGTH: u32 = 10;
+/// Points for each character between a pattern and the end of a tripcode, favoring patterns
+/// found near the start.
+const POSITION: u32 = 2;
+/// Points for each character of a pattern found in the same case as given.
+const CASE: u32 = 5;
+/// Points for each pattern found after the first.
+const EXTRA_PATTERN: u32 = 15;
+/// Points for each letter outside the patterns found.
+const READABLE: u32 = 1;
+
+/// Scores a tripcode by how well it matches `patterns`, or zero if it contains none of them.
+///
+/// Longer patterns score higher, as do patterns found near the start of the tripcode, patterns
+/// found in the same case as given (when matching regardless of case), tripcodes containing more
+/// than one pattern and tripcodes whose other characters are letters rather than digits or
+/// punctuation.
+pub fn score(patterns: &Patterns, tripcode: &str) -> u32 {
+    let found = patterns.find_all(tripcode);
+
+    if found.is_empty() {
+        return 0;
+    }
+
+    let tripcode = tripcode.as_bytes();
+    let mut covered = vec![false; tripcode.len()];
+    let mut score = EXTRA_PATTERN * (found.len() as u32 - 1);
+
+    for &(pattern, offset) in &found {
+        let exact = tripcode[offset..]
+            .iter()
+            .zip(pattern.bytes())
+            .filter(|&(&a, b)| a == b)
+            .count();
+
+        score += LENGTH * pattern.len() as u32;
+        score += POSITION * (tripcode.len() - offset) as u32;
+        score += CASE * exact as u32;
+
+        for c in &mut covered[offset..offset + pattern.len()] {
+            *c = true;
+        }
+    }
+
+    let readable = tripcode.iter()
+        .zip(&covered)
+        .filter(|&(c, &covered)| !covered && c.is_ascii_alphabetic())
+        .count();
+
+    score + READABLE * readable as u32
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub score: u32,
+    pub tripcode: Tripcode,
+    pub passwd: Password,
+}
+
+/// The highest scoring matches found so far.
+pub struct Leaderboard {
+    capacity: usize,
+    best: u32,
+    entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    /// A leaderboard keeping the best `capacity` matches.
+    pub fn new(capacity: usize) -> Leaderboard {
+        Leaderboard { capacity, best: 0, entries: Vec::new() }
+    }
+
+    /// Offers a match to the leaderboard, returning whether it scores higher than every match
+    /// offered before it.
+    pub fn offer(&mut self, score: u32, tripcode: &Tripcode, passwd: &Password) -> bool {
+        let best = score > self.best;
+
+        if best {
+            self.best = score;
+        }
+
+        if self.entries.iter().any(|e| e.tripcode == *tripcode) {
+            return best;
+        }
+
+        let i = self.entries.iter().position(|e| e.score < score).unwrap_or(self.entries.len());
+
+        if i < self.capacity {
+            self.entries.insert(i, Entry { score, tripcode: *tripcode, passwd: passwd.clone() });
+
+            self.entries.truncate(self.capacity);
+        }
+
+        best
+    }
+
+    /// The best matches, highest scoring first.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranking() {
+        let patterns = Patterns::new(vec!["Admin".to_string(), "jS".to_string()], true);
+        let start = score(&patterns, "Admin.0123");
+
+        assert_eq!(score(&patterns, "0123456789"), 0);
+        assert_eq!(start, 50 + 20 + 25);
+        assert_eq!(score(&patterns, "0123admin."), 50 + 12 + 20);
+        assert_eq!(score(&patterns, "Admin.abcd"), start + 4);
+        assert_eq!(score(&patterns, "Admin.0jS1"), start + 15 + 20 + 6 + 10);
+    }
+
+    #[test]
+    fn leaderboard() {
+        let mut board = Leaderboard::new(2);
+        let mut offer = |score, tripcode: &str, passwd: &str| {
+            board.offer(score, &tripcode.parse().unwrap(), &Password::from(passwd))
+        };
+
+        assert!(offer(10, "aaaaaaaaaa", "1"));
+        assert!(!offer(5, "bbbbbbbbbb", "2"));
+        assert!(offer(20, "cccccccccc", "3"));
+        assert!(!offer(20, "cccccccccc", "3"));
+        assert!(!offer(1, "dddddddddd", "4"));
+
+        let tripcodes: Vec<_> = board.entries().iter().map(|e| e.tripcode.as_str()).collect();
+        assert_eq!(tripcodes, vec!["cccccccccc", "aaaaaaaaaa"]);
+    }
+}
@@ -0,0 +1,363 @@
+use candidate::CandidateSource;
+use pattern::Patterns;
+use std::error;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use trip::{trip_with, Profile};
+use tripcode::Tripcode;
+use zeroize::Zeroizing;
+
+const BATCH_SIZE: usize = 1024;
+
+/// Why a search ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Exhausted,
+    Enter,
+    Signal,
+    Duration,
+    MaxCandidates,
+    MaxMatches,
+    Cancelled,
+    Error,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Stop::Exhausted => "every candidate was tried",
+            Stop::Enter => "the Enter key was pressed",
+            Stop::Signal => "a signal was received",
+            Stop::Duration => "the time limit was reached",
+            Stop::MaxCandidates => "the candidate limit was reached",
+            Stop::MaxMatches => "the match limit was reached",
+            Stop::Cancelled => "the search was cancelled",
+            Stop::Error => "an error occurred",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub duration: Option<Duration>,
+    pub candidates: Option<u64>,
+    pub matches: Option<u64>,
+}
+
+/// The state of a search shared between the workers and the thread supervising them.
+pub struct State {
+    limits: Limits,
+    start: Instant,
+    abort: AtomicBool,
+    stop: Mutex<Option<Stop>>,
+    candidates: AtomicU64,
+    matches: AtomicU64,
+    workers: Vec<AtomicU64>,
+}
+
+impl State {
+    pub fn new(limits: Limits, workers: usize) -> State {
+        State {
+            limits,
+            start: Instant::now(),
+            abort: AtomicBool::new(false),
+            stop: Mutex::new(None),
+            candidates: AtomicU64::new(0),
+            matches: AtomicU64::new(0),
+            workers: (0..workers).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Tells the workers to stop. Only the first reason given is kept.
+    pub fn stop(&self, reason: Stop) {
+        let mut stop = self.stop.lock().unwrap();
+
+        if stop.is_none() {
+            *stop = Some(reason);
+        }
+
+        self.abort.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the search if it has reached the duration limit.
+    pub fn check_duration(&self) {
+        if self.limits.duration.is_some_and(|d| self.start.elapsed() >= d) {
+            self.stop(Stop::Duration);
+        }
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.abort.load(Ordering::Relaxed)
+    }
+
+    /// Why the search stopped, or `Stop::Exhausted` if nothing stopped it.
+    pub fn reason(&self) -> Stop {
+        self.stop.lock().unwrap().unwrap_or(Stop::Exhausted)
+    }
+
+    /// Counts `n` candidates about to be tried by `worker`, returning how many of them fit within
+    /// the candidate limit.
+    pub fn claim(&self, worker: usize, n: usize) -> usize {
+        let before = self.candidates.fetch_add(n as u64, Ordering::Relaxed);
+
+        let claimed = match self.limits.candidates {
+            Some(max) if before + n as u64 >= max => {
+                self.stop(Stop::MaxCandidates);
+                max.saturating_sub(before) as usize
+            }
+            _ => n,
+        };
+
+        self.workers[worker].fetch_add(claimed as u64, Ordering::Relaxed);
+        claimed
+    }
+
+    /// Counts a match, returning whether it fits within the match limit.
+    pub fn record_match(&self) -> bool {
+        let before = self.matches.fetch_add(1, Ordering::Relaxed);
+
+        match self.limits.matches {
+            Some(max) if before + 1 >= max => {
+                self.stop(Stop::MaxMatches);
+                before < max
+            }
+            _ => true,
+        }
+    }
+
+    pub fn candidates(&self) -> u64 {
+        let candidates = self.candidates.load(Ordering::Relaxed);
+        self.limits.candidates.map_or(candidates, |max| candidates.min(max))
+    }
+
+    pub fn matches(&self) -> u64 {
+        let matches = self.matches.load(Ordering::Relaxed);
+        self.limits.matches.map_or(matches, |max| matches.min(max))
+    }
+
+    /// The number of candidates tried by each worker.
+    pub fn worker_candidates(&self) -> Vec<u64> {
+        self.workers.iter().map(|w| w.load(Ordering::Relaxed)).collect()
+    }
+}
+
+/// A candidate whose tripcode contains a pattern.
+pub struct Found<'a> {
+    pub worker: usize,
+    pub passwd: &'a [u8; 8],
+    pub tripcode: Tripcode,
+    /// The first pattern found in the tripcode, and the offset of its first occurrence.
+    pub pattern: &'a str,
+    pub offset: usize,
+}
+
+/// Tries the candidates of `source` as worker `worker` until they run out or the search stops,
+/// passing those whose tripcodes contain a pattern to `found`. `found` decides which of them
+/// count towards the match limit by calling `State::record_match`.
+///
+/// An error reading candidates or returned by `found` stops the search with `Stop::Error`.
+pub fn run<E, F>(
+    worker: usize,
+    mut source: Box<dyn CandidateSource>,
+    patterns: &Patterns,
+    profile: Profile,
+    state: &State,
+    mut found: F,
+) -> Result<(), E>
+    where E: From<io::Error>,
+          F: FnMut(Found) -> Result<(), E>
+{
+    let mut batch = Zeroizing::new(Vec::with_capacity(BATCH_SIZE));
+
+    let result = (|| {
+        while !state.aborted() {
+            state.check_duration();
+            batch.clear();
+            let more = source.fill(&mut batch, BATCH_SIZE)?;
+            let claimed = state.claim(worker, batch.len());
+
+            for passwd in &batch[..claimed] {
+                let tripcode = match trip_with(*passwd, profile) {
+                    Some(tripcode) => tripcode,
+                    None => continue,
+                };
+
+                if let Some((pattern, offset)) = patterns.find(tripcode.as_str()) {
+                    found(Found { worker, passwd, tripcode, pattern, offset })?;
+                }
+            }
+
+            if !more {
+                break;
+            }
+        }
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        state.stop(Stop::Error);
+    }
+
+    result
+}
+
+/// The error returned when the value of an option can't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseArgError {
+    /// A value not of the form expected, such as `2x` for a duration.
+    Invalid(&'static str, String),
+    /// A value of the form expected which is too large to be represented.
+    TooLarge(&'static str, String),
+    /// A name which isn't one of the choices, such as an unknown output format.
+    Unknown(&'static str, String),
+}
+
+impl fmt::Display for ParseArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseArgError::Invalid(what, ref s) => write!(f, "invalid {} {}", what, s),
+            ParseArgError::TooLarge(what, ref s) => write!(f, "{} {} is too large", what, s),
+            ParseArgError::Unknown(what, ref s) => write!(f, "unknown {} {}", what, s),
+        }
+    }
+}
+
+impl error::Error for ParseArgError {}
+
+/// Parses a duration such as `90`, `30s`, `15m`, `2h` or `1d`.
+pub fn parse_duration(s: &str) -> Result<Duration, ParseArgError> {
+    let err = || ParseArgError::Invalid("duration", s.to_string());
+
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+
+    let secs: u64 = num.parse().map_err(|_| err())?;
+
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(err()),
+    };
+
+    secs.checked_mul(scale)
+        .map(Duration::from_secs)
+        .ok_or_else(|| ParseArgError::TooLarge("duration", s.to_string()))
+}
+
+/// Parses a count such as `1000000` or `1e6`.
+pub fn parse_count(s: &str) -> Result<u64, ParseArgError> {
+    let err = || ParseArgError::Invalid("count", s.to_string());
+
+    match s.find(['e', 'E']) {
+        Some(i) => {
+            let mantissa: u64 = s[..i].parse().map_err(|_| err())?;
+            let exponent: u32 = s[i + 1..].parse().map_err(|_| err())?;
+
+            10u64.checked_pow(exponent)
+                .and_then(|scale| mantissa.checked_mul(scale))
+                .ok_or_else(err)
+        }
+        None => s.parse().map_err(|_| err()),
+    }
+}
+
+/// Parses a part of a keyspace such as `2/8`, returning it counted from zero.
+pub fn parse_part(s: &str) -> Result<(u64, u64), ParseArgError> {
+    let err = || ParseArgError::Invalid("part", s.to_string());
+    let mut nums = s.splitn(2, '/').map(|n| n.parse::<u64>().map_err(|_| err()));
+
+    match (nums.next(), nums.next()) {
+        (Some(k), Some(n)) => {
+            let (k, n) = (k?, n?);
+
+            if k == 0 || k > n {
+                return Err(err());
+            }
+
+            Ok((k - 1, n))
+        }
+        _ => Err(err()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let state = State::new(Limits {
+            candidates: Some(1500),
+            matches: Some(2),
+            ..Limits::default()
+        }, 2);
+        assert_eq!(state.claim(0, 1024), 1024);
+        assert!(!state.aborted());
+        assert!(state.record_match());
+        assert_eq!(state.claim(1, 1024), 476);
+        assert_eq!(state.reason(), Stop::MaxCandidates);
+        assert!(state.record_match());
+        assert!(!state.record_match());
+        assert_eq!(state.reason(), Stop::MaxCandidates);
+        assert_eq!(state.candidates(), 1500);
+        assert_eq!(state.matches(), 2);
+        assert_eq!(state.worker_candidates(), vec![1024, 476]);
+    }
+
+    #[test]
+    fn run_finds_matches() {
+        use candidate::{Lines, Mask};
+        use std::env;
+
+        let patterns = Patterns::new(vec!["vcto".to_string()], false);
+        let state = State::new(Limits::default(), 1);
+        let mask = Box::new(Mask::parse("foofoof?l").unwrap());
+        let mut found = Vec::new();
+
+        run(0, mask, &patterns, Profile::TWO_CHANNEL, &state, |m| {
+            found.push((*m.passwd, m.tripcode.to_string(), m.pattern.to_string(), m.offset));
+            Ok::<_, io::Error>(())
+        }).unwrap();
+
+        assert_eq!(found, vec![(*b"foofoofo", "vctoKCJ4Fk".to_string(), "vcto".to_string(), 0)]);
+        assert_eq!(state.candidates(), 26);
+        assert!(!state.aborted());
+
+        let lines = Box::new(Lines::open(env::temp_dir()).unwrap());
+        let result: io::Result<()> =
+            run(0, lines, &patterns, Profile::TWO_CHANNEL, &state, |_| Ok(()));
+        assert!(result.is_err());
+        assert_eq!(state.reason(), Stop::Error);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("2x").unwrap_err().to_string(), "invalid duration 2x");
+        assert_eq!(
+            parse_duration("999999999999999999d"),
+            Err(ParseArgError::TooLarge("duration", "999999999999999999d".to_string()))
+        );
+        assert_eq!(parse_count("1e12"), Ok(1_000_000_000_000));
+        assert_eq!(parse_count("10"), Ok(10));
+        assert!(parse_count("1e20").is_err());
+        assert_eq!(parse_part("1/4"), Ok((0, 4)));
+        assert_eq!(parse_part("4/4"), Ok((3, 4)));
+        assert!(parse_part("0/4").is_err());
+        assert!(parse_part("5/4").is_err());
+        assert!(parse_part("4").is_err());
+    }
+}
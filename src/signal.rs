@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn handle(sig: ::libc::c_int) {
+    RECEIVED.store(true, Ordering::SeqCst);
+
+    // A second signal terminates the process, in case the first one is not acted on.
+    unsafe {
+        ::libc::signal(sig, ::libc::SIG_DFL);
+    }
+}
+
+/// Records the first SIGINT or SIGTERM instead of letting it terminate the process.
+#[cfg(unix)]
+pub fn install() {
+    for &sig in &[::libc::SIGINT, ::libc::SIGTERM] {
+        unsafe {
+            ::libc::signal(sig, handle as extern "C" fn(::libc::c_int) as ::libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// Whether SIGINT or SIGTERM has been received since `install` was called.
+pub fn received() -> bool {
+    RECEIVED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,181 @@
+use password::Password;
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use tripcode::Tripcode;
+use zeroize::Zeroizing;
+
+/// An append-only file of matches keyed by tripcode, shared between runs.
+///
+/// Each line holds a tripcode, its password and the time it was found, separated by tabs.
+/// Bytes of the password outside printable ASCII, and backslashes, are written as `\xNN`. Lines
+/// which cannot be parsed, such as one truncated by a crash, are ignored.
+pub struct Store {
+    file: File,
+    known: BTreeMap<Tripcode, Password>,
+}
+
+impl Store {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Store> {
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        let mut known = BTreeMap::new();
+        let mut complete = true;
+        let mut reader = BufReader::new(&file);
+        let mut line = Zeroizing::new(Vec::new());
+
+        loop {
+            line.clear();
+
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+
+            complete = line.last() == Some(&b'\n');
+
+            if complete {
+                line.pop();
+            }
+
+            let mut fields = line.split(|&c| c == b'\t');
+            let tripcode = fields.next().and_then(|tripcode| Tripcode::new(tripcode).ok());
+            let passwd = fields.next().and_then(unescape).map(Password::from);
+
+            if let (Some(tripcode), Some(passwd)) = (tripcode, passwd) {
+                known.entry(tripcode).or_insert(passwd);
+            }
+        }
+
+        // Start a new line after a truncated record rather than appending to it.
+        if !complete {
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Store { file, known })
+    }
+
+    pub fn contains(&self, tripcode: &Tripcode) -> bool {
+        self.known.contains_key(tripcode)
+    }
+
+    /// Records a match found at `timestamp`, returning `false` without recording it if the
+    /// tripcode is already known.
+    pub fn insert(
+        &mut self,
+        tripcode: &Tripcode,
+        passwd: &Password,
+        timestamp: &str,
+    ) -> io::Result<bool> {
+        if self.known.contains_key(tripcode) {
+            return Ok(false);
+        }
+
+        // A single write keeps records from concurrent runs from interleaving. The record is
+        // allocated at its full size, so that it is never moved, leaving a copy behind.
+        let escaped = escape(passwd.as_bytes());
+        let len = tripcode.as_bytes().len() + escaped.len() + timestamp.len() + 3;
+        let mut record = Zeroizing::new(Vec::with_capacity(len));
+
+        for field in [tripcode.as_bytes(), escaped.as_bytes(), timestamp.as_bytes()] {
+            record.extend_from_slice(field);
+            record.push(b'\t');
+        }
+
+        record.pop();
+        record.push(b'\n');
+        self.file.write_all(&record)?;
+        self.known.insert(*tripcode, passwd.clone());
+        Ok(true)
+    }
+
+    /// Every known tripcode containing `pattern`, with its password, in tripcode order.
+    pub fn query<'a>(&'a self, pattern: &'a str)
+        -> impl Iterator<Item = (&'a Tripcode, &'a Password)> + 'a
+    {
+        self.known.iter().filter(move |&(tripcode, _)| tripcode.as_str().contains(pattern))
+    }
+}
+
+fn escape(passwd: &[u8]) -> Zeroizing<String> {
+    // Each byte is escaped as at most `\xNN`.
+    let mut escaped = Zeroizing::new(String::with_capacity(passwd.len() * 4));
+
+    for &c in passwd {
+        if c == b'\\' || !(b' '..=b'~').contains(&c) {
+            write!(escaped, "\\x{:02x}", c).unwrap();
+        } else {
+            escaped.push(c as char);
+        }
+    }
+
+    escaped
+}
+
+fn unescape(escaped: &[u8]) -> Option<Vec<u8>> {
+    let mut passwd = Vec::with_capacity(escaped.len());
+    let mut bytes = escaped.iter();
+
+    while let Some(&c) = bytes.next() {
+        if c != b'\\' {
+            passwd.push(c);
+            continue;
+        }
+
+        if bytes.next() != Some(&b'x') {
+            return None;
+        }
+
+        let hex = [*bytes.next()?, *bytes.next()?];
+        let hex = ::std::str::from_utf8(&hex).ok()?;
+        passwd.push(u8::from_str_radix(hex, 16).ok()?);
+    }
+
+    Some(passwd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn dedup_across_opens() {
+        let path = env::temp_dir().join(format!("trip-store-test-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let tripcode = |s: &str| s.parse::<Tripcode>().unwrap();
+        let passwd = |bytes: &[u8]| Password::from(bytes);
+        let insert = |store: &mut Store, t, p| store.insert(&tripcode(t), &passwd(p), "t").unwrap();
+
+        {
+            let mut store = Store::open(&path).unwrap();
+            assert!(insert(&mut store, "vctoKCJ4Fk", b"foofoofo"));
+            assert!(!insert(&mut store, "vctoKCJ4Fk", b"other"));
+            assert!(insert(&mut store, "sJh8mwqDUo", b"\\\t\xff"));
+        }
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"trunc\t\\x4").unwrap();
+
+        {
+            let mut store = Store::open(&path).unwrap();
+            assert!(!insert(&mut store, "sJh8mwqDUo", b"x"));
+            assert!(insert(&mut store, "85qvGhCCNc", b"ab"));
+        }
+
+        let store = Store::open(&path).unwrap();
+        let all: Vec<_> = store.query("").map(|(t, p)| (*t, p.clone())).collect();
+
+        assert_eq!(all, vec![
+            (tripcode("85qvGhCCNc"), passwd(b"ab")),
+            (tripcode("sJh8mwqDUo"), passwd(b"\\\t\xff")),
+            (tripcode("vctoKCJ4Fk"), passwd(b"foofoofo")),
+        ]);
+
+        assert_eq!(store.query("KCJ").count(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
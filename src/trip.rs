@@ -53,13 +53,28 @@
  * by David Burren.  It has been heavily re-worked by Solar Designer.
  */
 
-use std::num::Wrapping;
+use core::num::Wrapping;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+use tripcode::Tripcode;
+use zeroize::{Zeroize, Zeroizing};
 
 struct ExpandedKey {
     l: [u32; 16],
     r: [u32; 16],
 }
 
+impl Drop for ExpandedKey {
+    fn drop(&mut self) {
+        self.l.zeroize();
+        self.r.zeroize();
+    }
+}
+
 fn ascii_to_bin(ch: i32) -> u32 {
     let sch = if ch < 0x80 { ch } else { -(0x100 - ch) };
 
@@ -76,13 +91,278 @@ fn ascii_to_bin(ch: i32) -> u32 {
     retval as u32 & 0x3f
 }
 
-pub fn trip(passwd: [u8; 8]) -> [u8; 10] {
-    let mut keybuf = [0u8; 8];
+/// The second and third characters of a password, with `H.` standing in for missing ones and a
+/// NUL after that for the empty password.
+fn salt_chars(passwd: &[u8]) -> [u8; 2] {
+    let mut chars = passwd.iter().take_while(|&&c| c != 0).chain(b"H.\0").skip(1);
+    [*chars.next().unwrap(), *chars.next().unwrap()]
+}
+
+/// Maps a salt character into `./0-9A-Za-z` the way 2channel does.
+fn map_salt_char(c: u8) -> u8 {
+    (match c as char {
+        '/' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | 'A' | 'B' | 'C' |
+        'D' | 'E' | 'F' | 'G' | 'H' | 'I' | 'J' | 'K' | 'L' | 'M' | 'N' | 'O' | 'P' | 'Q' |
+        'R' | 'S' | 'T' | 'U' | 'V' | 'W' | 'X' | 'Y' | 'Z' | 'a' | 'b' | 'c' | 'd' | 'e' |
+        'f' | 'g' | 'h' | 'i' | 'j' | 'k' | 'l' | 'm' | 'n' | 'o' | 'p' | 'q' | 'r' | 's' |
+        't' | 'u' | 'v' | 'w' | 'x' | 'y' | 'z' => c as char,
+        ':' => 'A',
+        ';' => 'B',
+        '<' => 'C',
+        '=' => 'D',
+        '>' => 'E',
+        '?' => 'F',
+        '@' => 'G',
+        '[' => 'a',
+        '\\' => 'b',
+        ']' => 'c',
+        '^' => 'd',
+        '_' => 'e',
+        '`' => 'f',
+        _ => '.',
+    }) as u8
+}
+
+/// The salt 2channel derives from a password: its second and third characters, with `H.`
+/// standing in for missing ones and characters outside `./0-9A-Za-z` mapped into it.
+fn tripcode_salt(passwd: &[u8]) -> [u8; 2] {
+    let [c0, c1] = salt_chars(passwd);
+    [map_salt_char(c0), map_salt_char(c1)]
+}
+
+/// A crypt(3) implementation whose handling of salt characters outside `./0-9A-Za-z` is
+/// reproduced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compat {
+    /// FreeSec as found in musl and in PHP before 8.0: characters are decoded arithmetically and
+    /// wrapped into six bits, except for NUL, newline and `:`, which are refused.
+    FreeSec,
+    /// Current glibc, libxcrypt and PHP 8: every invalid character is refused.
+    Strict,
+    /// UFC-crypt in older glibc, which decodes like FreeSec but refuses nothing.
+    Ufc,
+    /// UFC-crypt built where `char` is unsigned, as on ARM and PowerPC, which decodes bytes with
+    /// the high bit set differently.
+    UfcUnsigned,
+    /// The original FreeSec kept by FreeBSD and NetBSD, which decodes invalid characters as `.`.
+    Bsd,
+}
+
+/// The error returned when parsing an unknown `Compat` name.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseCompatError {
+    name: String,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseCompatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown crypt implementation {}", self.name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ParseCompatError {}
+
+#[cfg(feature = "std")]
+impl FromStr for Compat {
+    type Err = ParseCompatError;
+
+    fn from_str(s: &str) -> Result<Compat, ParseCompatError> {
+        match s {
+            "freesec" => Ok(Compat::FreeSec),
+            "strict" => Ok(Compat::Strict),
+            "ufc" => Ok(Compat::Ufc),
+            "ufc-unsigned" => Ok(Compat::UfcUnsigned),
+            "bsd" => Ok(Compat::Bsd),
+            _ => Err(ParseCompatError { name: s.to_string() }),
+        }
+    }
+}
+
+impl Compat {
+    /// The six bits salt character `c` stands for, or `None` if the implementation refuses it.
+    fn salt_bits(self, c: u8) -> Option<u32> {
+        match self {
+            Compat::FreeSec if c == 0 || c == b'\n' || c == b':' => None,
+            Compat::FreeSec | Compat::Ufc => Some(ascii_to_bin(c as i32)),
+            Compat::Strict => ASCII64.iter().position(|&a| a == c).map(|i| i as u32),
+            Compat::UfcUnsigned => {
+                let c = c as u32;
+
+                Some(match c {
+                    _ if c >= 'a' as u32 => c - 59,
+                    _ if c >= 'A' as u32 => c - 53,
+                    _ => c.wrapping_sub('.' as u32),
+                } & 0x3f)
+            }
+            Compat::Bsd => Some(match c {
+                b'.'..=b'9' => c - b'.',
+                b'A'..=b'Z' => c - b'A' + 12,
+                b'a'..=b'z' => c - b'a' + 38,
+                _ => 0,
+            } as u32),
+        }
+    }
+}
+
+/// How a board turns a password into a tripcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// Whether salt characters are mapped into `./0-9A-Za-z` as 2channel does before they reach
+    /// crypt(3), in which case the choice of `compat` makes no difference.
+    pub map_salt: bool,
+    pub compat: Compat,
+}
 
-    for (i, val) in passwd.iter().take(keybuf.len()).enumerate() {
+impl Profile {
+    /// 2channel's rules, which `trip` follows.
+    pub const TWO_CHANNEL: Profile = Profile { map_salt: true, compat: Compat::FreeSec };
+}
+
+/// The tripcode of `passwd` under `profile`, or `None` if its crypt(3) refuses the salt.
+pub fn trip_with(passwd: [u8; 8], profile: Profile) -> Option<Tripcode> {
+    let [mut c0, mut c1] = salt_chars(&passwd);
+
+    if profile.map_salt {
+        c0 = map_salt_char(c0);
+        c1 = map_salt_char(c1);
+    }
+
+    let salt = profile.compat.salt_bits(c1)? << 6 | profile.compat.salt_bits(c0)?;
+    Some(trip_salted(passwd, salt))
+}
+
+pub fn trip(passwd: [u8; 8]) -> Tripcode {
+    let [setting0, setting1] = tripcode_salt(&passwd);
+    trip_salted(passwd, ascii_to_bin(setting1 as i32) << 6 | ascii_to_bin(setting0 as i32))
+}
+
+fn trip_salted(passwd: [u8; 8], salt: u32) -> Tripcode {
+    let passwd = Zeroizing::new(passwd);
+    let ekey = des_setkey(&key_bytes(&*passwd));
+    let (r0, r1) = do_des(&ekey, 0, 0, setup_salt(salt), 25);
+    Tripcode::new_unchecked(&encode(r0, r1)[1..])
+}
+
+/// The error returned by `crypt` for a setting it can't hash with.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CryptError {
+    setting: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for CryptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid setting {:?}", String::from_utf8_lossy(&self.setting))
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for CryptError {}
+
+/// Hashes `key` with the DES-based crypt(3), returning the setting followed by the hash.
+///
+/// A setting starting with `_` selects the extended BSDi format: four characters of iteration
+/// count and four of salt, each encoding six bits least significant first, for a 20 character
+/// result. The whole key is used, up to any NUL byte.
+///
+/// Otherwise the traditional format is used: the setting must start with two characters of
+/// salt, and any after them are ignored, for a 13 character result. Only the first eight bytes
+/// of the key are used.
+///
+/// Every character of the count and salt must be in `./0-9A-Za-z`, as FreeSec and libxcrypt
+/// require, so that only hashes a real system could have produced are accepted.
+#[cfg(feature = "std")]
+pub fn crypt(key: &[u8], setting: &[u8]) -> Result<String, CryptError> {
+    if setting.first() == Some(&b'_') {
+        return crypt_extended(key, setting);
+    }
+
+    let (setting0, setting1) = match *setting {
+        [s0, s1, ..] if is_setting_char(s0) && is_setting_char(s1) => (s0, s1),
+        _ => return Err(CryptError { setting: setting.to_vec() }),
+    };
+
+    let ekey = des_setkey(&key_bytes(key));
+    let salt = ascii_to_bin(setting1 as i32) << 6 | ascii_to_bin(setting0 as i32);
+    let (r0, r1) = do_des(&ekey, 0, 0, setup_salt(salt), 25);
+
+    let mut output = String::with_capacity(13);
+    output.push(setting0 as char);
+    output.push(setting1 as char);
+    output.extend(encode(r0, r1).iter().map(|&c| c as char));
+    Ok(output)
+}
+
+/// Whether `c` is in `./0-9A-Za-z`, checked as FreeSec does: by whether it decodes and encodes
+/// back to itself.
+#[cfg(feature = "std")]
+fn is_setting_char(c: u8) -> bool {
+    ASCII64[ascii_to_bin(c as i32) as usize] == c
+}
+
+#[cfg(feature = "std")]
+fn crypt_extended(key: &[u8], setting: &[u8]) -> Result<String, CryptError> {
+    let invalid = || CryptError { setting: setting.to_vec() };
+
+    let setting = match setting.get(..9) {
+        Some(s) if s[1..].iter().all(|&c| is_setting_char(c)) => s,
+        _ => return Err(invalid()),
+    };
+
+    let decode = |chars: &[u8]| {
+        chars.iter().rev().fold(0, |n, &c| n << 6 | ascii_to_bin(c as i32))
+    };
+
+    let count = decode(&setting[1..5]);
+    let salt = decode(&setting[5..9]);
+
+    if count == 0 {
+        return Err(invalid());
+    }
+
+    let mut keybuf = key_bytes(key);
+    let mut ekey = des_setkey(&keybuf);
+    let len = key.iter().position(|&c| c == 0).unwrap_or(key.len());
+
+    // Fold the rest of the key in eight bytes at a time, encrypting the key so far with itself.
+    for chunk in key[len.min(8)..len].chunks(8) {
+        let l = u32::from_be_bytes([keybuf[0], keybuf[1], keybuf[2], keybuf[3]]);
+        let r = u32::from_be_bytes([keybuf[4], keybuf[5], keybuf[6], keybuf[7]]);
+        let (l, r) = do_des(&ekey, l, r, 0, 1);
+        keybuf[..4].copy_from_slice(&l.to_be_bytes());
+        keybuf[4..].copy_from_slice(&r.to_be_bytes());
+
+        for (k, &c) in keybuf.iter_mut().zip(chunk) {
+            *k ^= c << 1;
+        }
+
+        ekey = des_setkey(&keybuf);
+    }
+
+    let (r0, r1) = do_des(&ekey, 0, 0, setup_salt(salt), count);
+
+    let mut output = String::with_capacity(20);
+    output.extend(setting.iter().map(|&c| c as char));
+    output.extend(encode(r0, r1).iter().map(|&c| c as char));
+    Ok(output)
+}
+
+/// The first eight bytes of a key, up to any NUL byte, each shifted into the top seven bits.
+fn key_bytes(key: &[u8]) -> Zeroizing<[u8; 8]> {
+    let mut keybuf = Zeroizing::new([0u8; 8]);
+
+    for (i, val) in key.iter().take(8).take_while(|&&c| c != 0).enumerate() {
         keybuf[i] = val << 1;
     }
 
+    keybuf
+}
+
+fn des_setkey(keybuf: &[u8; 8]) -> ExpandedKey {
     let mut ekey = ExpandedKey {
         l: [0; 16],
         r: [0; 16],
@@ -115,11 +395,11 @@ pub fn trip(passwd: [u8; 8]) -> [u8; 10] {
 
     let mut shifts = 0usize;
 
-    for round in 0usize..16 {
-        shifts += KEY_SHIFTS[round] as usize;
+    for (round, &shift) in KEY_SHIFTS.iter().enumerate() {
+        shifts += shift as usize;
 
-        let t0 = k0 << shifts | k0 >> 28 - shifts;
-        let t1 = k1 << shifts | k1 >> 28 - shifts;
+        let t0 = k0 << shifts | k0 >> (28 - shifts);
+        let t1 = k1 << shifts | k1 >> (28 - shifts);
 
         let mut kl = 0u32;
         let mut kr = 0u32;
@@ -138,31 +418,11 @@ pub fn trip(passwd: [u8; 8]) -> [u8; 10] {
         ekey.r[round] = kr;
     }
 
-    let mut salt_chars = passwd.iter().chain(b"H.").skip(1).map(|&c| match c as char {
-        '/' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | 'A' | 'B' | 'C' |
-        'D' | 'E' | 'F' | 'G' | 'H' | 'I' | 'J' | 'K' | 'L' | 'M' | 'N' | 'O' | 'P' | 'Q' |
-        'R' | 'S' | 'T' | 'U' | 'V' | 'W' | 'X' | 'Y' | 'Z' | 'a' | 'b' | 'c' | 'd' | 'e' |
-        'f' | 'g' | 'h' | 'i' | 'j' | 'k' | 'l' | 'm' | 'n' | 'o' | 'p' | 'q' | 'r' | 's' |
-        't' | 'u' | 'v' | 'w' | 'x' | 'y' | 'z' => c as char,
-        ':' => 'A',
-        ';' => 'B',
-        '<' => 'C',
-        '=' => 'D',
-        '>' => 'E',
-        '?' => 'F',
-        '@' => 'G',
-        '[' => 'a',
-        '\\' => 'b',
-        ']' => 'c',
-        '^' => 'd',
-        '_' => 'e',
-        '`' => 'f',
-        _ => '.',
-    } as u8);
+    ekey
+}
 
-    let setting0 = salt_chars.next().unwrap();
-    let setting1 = salt_chars.next().unwrap();
-    let salt = ascii_to_bin(setting1 as i32) << 6 | ascii_to_bin(setting0 as i32);
+/// Spreads the 12 bits of a salt over the 24 bits of the expansion it swaps.
+fn setup_salt(salt: u32) -> u32 {
     let mut saltbits = 0u32;
     let mut saltbit = 1u32;
     let mut obit = 0x800000;
@@ -176,10 +436,28 @@ pub fn trip(passwd: [u8; 8]) -> [u8; 10] {
         obit >>= 1;
     }
 
-    let mut l = 0u32;
-    let mut r = 0u32;
+    saltbits
+}
+
+/// Encrypts a block `count` times, returning it after the final permutation.
+fn do_des(ekey: &ExpandedKey, l_in: u32, r_in: u32, saltbits: u32, count: u32) -> (u32, u32) {
+    let (mut l, mut r) = (0u32, 0u32);
+
+    // The initial permutation of a zero block, which crypt(3) encrypts, is a zero block.
+    if l_in | r_in != 0 {
+        for (i, &bit) in IP.iter().enumerate() {
+            let bit = bit as usize - 1;
+            let set = if bit < 32 { l_in >> (31 - bit) & 1 } else { r_in >> (63 - bit) & 1 };
+
+            if i < 32 {
+                l |= set << (31 - i);
+            } else {
+                r |= set << (63 - i);
+            }
+        }
+    }
 
-    for _ in 0..25 {
+    for _ in 0..count {
         let mut f = 0u32;
 
         for (kl, kr) in ekey.l.iter().zip(ekey.r.iter()) {
@@ -222,32 +500,114 @@ pub fn trip(passwd: [u8; 8]) -> [u8; 10] {
         ibit = (Wrapping(ibit) - Wrapping(4)).0;
     }
 
-    let mut output = [0u8; 10];
+    (r0, r1)
+}
+
+/// Encodes a block as 11 characters of the crypt(3) alphabet.
+fn encode(r0: u32, r1: u32) -> [u8; 11] {
+    let mut output = [0u8; 11];
     let l = (r0 as usize) >> 8;
-    output[0] = ASCII64[l >> 12 & 0x3f];
-    output[1] = ASCII64[l >> 6 & 0x3f];
-    output[2] = ASCII64[l & 0x3f];
+    output[0] = ASCII64[l >> 18 & 0x3f];
+    output[1] = ASCII64[l >> 12 & 0x3f];
+    output[2] = ASCII64[l >> 6 & 0x3f];
+    output[3] = ASCII64[l & 0x3f];
     let l = ((r0 as usize) << 16) | ((r1 as usize) >> 16 & 0xffff);
-    output[3] = ASCII64[l >> 18 & 0x3f];
-    output[4] = ASCII64[l >> 12 & 0x3f];
-    output[5] = ASCII64[l >> 6 & 0x3f];
-    output[6] = ASCII64[l & 0x3f];
+    output[4] = ASCII64[l >> 18 & 0x3f];
+    output[5] = ASCII64[l >> 12 & 0x3f];
+    output[6] = ASCII64[l >> 6 & 0x3f];
+    output[7] = ASCII64[l & 0x3f];
     let l = (r1 as usize) << 2;
-    output[7] = ASCII64[l >> 12 & 0x3f];
-    output[8] = ASCII64[l >> 6 & 0x3f];
-    output[9] = ASCII64[l & 0x3f];
+    output[8] = ASCII64[l >> 12 & 0x3f];
+    output[9] = ASCII64[l >> 6 & 0x3f];
+    output[10] = ASCII64[l & 0x3f];
     output
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test::Bencher;
+/// An implementation of the tripcode function.
+pub type Engine = fn([u8; 8]) -> Tripcode;
+
+/// Every implementation of the tripcode function, by name.
+pub const ENGINES: &[(&str, Engine)] = &[("scalar", trip)];
+
+#[cfg(feature = "std")]
+const SELF_TEST_TRIPCODES: &[([u8; 8], &str)] = &[
+    (*b"foofoofo", "vctoKCJ4Fk"),
+    (*b"a\0\0\0\0\0\0\0", "ZnBI2EKkq."),
+    (*b"x`_^\0\0\0\0", "GKz7BFTQnA"),
+    (*b"\x82\xa0\x82\xa2\0\0\0\0", "8udsPUAZG2"),
+    (*b"\xff\xff\xff\xff\xff\xff\xff\xff", "eejHSflqus"),
+];
+
+/// The error returned by `self_test`: the first known answer not reproduced.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelfTestError {
+    /// An engine computed the wrong tripcode for a password.
+    Engine {
+        name: &'static str,
+        passwd: [u8; 8],
+        tripcode: Tripcode,
+        expected: &'static str,
+    },
+    /// `crypt` computed the wrong hash for a setting, or refused it.
+    Crypt {
+        setting: &'static [u8],
+        hash: Result<String, CryptError>,
+        expected: &'static str,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SelfTestError::Engine { name, ref passwd, ref tripcode, expected } => write!(
+                f,
+                "the {} engine computed {} for {:?} instead of {}",
+                name,
+                tripcode,
+                passwd,
+                expected
+            ),
+            SelfTestError::Crypt { ref hash, expected, .. } => {
+                write!(f, "crypt computed {:?} instead of {}", hash, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SelfTestError {}
+
+/// Checks every engine, and both formats of `crypt`, against known answers.
+///
+/// A port of the quick self-test FreeSec runs before hashing, to be run before a search so that
+/// a miscompiled engine can't go on producing wrong tripcodes.
+#[cfg(feature = "std")]
+pub fn self_test() -> Result<(), SelfTestError> {
+    for &(name, engine) in ENGINES {
+        for &(passwd, expected) in SELF_TEST_TRIPCODES {
+            let tripcode = engine(passwd);
+
+            if tripcode != expected {
+                return Err(SelfTestError::Engine { name, passwd, tripcode, expected });
+            }
+        }
+    }
 
-    #[bench]
-    fn bench_trip(b: &mut Bencher) {
-        b.iter(|| trip(*b"foofoofo"));
+    let key = b"\x80\xff\x80\x01 \x7f\x81\x80\x80\x0d\x0a\xff\x7f \x81 test";
+
+    for &(key, setting, expected) in &[
+        (&key[..], &b"_0.../9Zz"[..], "_0.../9ZzX7iSJNd21sU"),
+        (&b"test"[..], &b"aa"[..], "aaqPiZY5xR5l."),
+    ] {
+        match crypt(key, setting) {
+            Ok(ref hash) if hash == expected => {}
+            hash => return Err(SelfTestError::Crypt { setting, hash, expected }),
+        }
     }
+
+    Ok(())
 }
 
 const KEY_SHIFTS: [u8; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
@@ -1680,6 +2040,16 @@ const FP_MASKR: [[u32; 16]; 8] = [
     ],
 ];
 
+/// The initial permutation, giving for each bit of the output the bit of the input it comes
+/// from, counting from one at the most significant. Only needed for nonzero blocks, so it is
+/// applied a bit at a time rather than with tables.
+const IP: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4,
+    62, 54, 46, 38, 30, 22, 14, 6, 64, 56, 48, 40, 32, 24, 16, 8,
+    57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3,
+    61, 53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
 const ASCII64: [u8; 64] = [
     0x2e,
     0x2f,
@@ -1746,3 +2116,247 @@ const ASCII64: [u8; 64] = [
     0x79,
     0x7a,
 ];
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Passwords with the salts 2channel derives from them and their tripcodes, as computed by
+    /// glibc's crypt(3).
+    const KNOWN_ANSWERS: &[(&[u8], &[u8; 2], &str)] = &[
+        (b"foofoofo", b"oo", "vctoKCJ4Fk"),
+        (b"password", b"as", "ozOtJW9BFA"),
+        (b"istrip", b"st", "/WG5qp963c"),
+        (b"trip", b"ri", "hEpdoZ.tHU"),
+        (b"", b"..", "8NBuQ4l6uQ"),
+        (b"a", b"H.", "ZnBI2EKkq."),
+        (b"ab", b"bH", "85qvGhCCNc"),
+        (b"abc", b"bc", "GmgU93SCyE"),
+        (b"12345678", b"23", "WBRXcNtpf."),
+        (b"tea", b"ea", "WokonZwxw2"),
+        (b"Z", b"H.", "XUZ3/z7o3k"),
+        (b"a:;", b"AB", "6FsyncvrHk"),
+        (b"a<=", b"CD", "qa7ffCA/uA"),
+        (b"a>?", b"EF", "ZAzYo62wto"),
+        (b"a@[", b"Ga", "6yLzjFnhnQ"),
+        (b"a\\]", b"bc", "UpIWGmI6HA"),
+        (b"a^_", b"de", "YKEepj5ZnI"),
+        (b"a`", b"fH", "Hf2Pv3GfBE"),
+        (b"a:", b"AH", "/77gJ.2YMM"),
+        (b"::::", b"AA", "uZY7ieqq1E"),
+        (b"[[[[", b"aa", "R4yrqVJ3Lc"),
+        (b"x`_^", b"fe", "GKz7BFTQnA"),
+        (b"@@@", b"GG", "ELOhNgd7TE"),
+        (b"a!\"", b"..", ".Pf3ZWgtnE"),
+        (b"a#$", b"..", "WffBzmFQSk"),
+        (b"a%&", b"..", "d3RjuC67dw"),
+        (b"a'(", b"..", "HUx8n/xLZM"),
+        (b"a)*", b"..", "Eff1DBy0Gk"),
+        (b"a+,", b"..", "6b2GKw2ICw"),
+        (b"a-.", b"..", "ixVu3vDxuc"),
+        (b"a{|", b"..", "t6q9EwcZZo"),
+        (b"a}~", b"..", "hBnItOy.hA"),
+        (b"a  ", b"..", "X/aihWtdIQ"),
+        (b"a\x7f\x7f", b"..", "0bHbaGUd3I"),
+        (b"a\x09\x0a", b"..", "G3UdcRC.cs"),
+        (b"a\xe3\x81\x82", b"..", "zIMqz02U6E"),
+        (b"\xe3\x81\x82", b"..", "VTkshKs7a."),
+        (b"\x82\xa0\x82\xa2", b"..", "8udsPUAZG2"),
+        (b"\xff\xff\xff\xff\xff\xff\xff\xff", b"..", "eejHSflqus"),
+        (b"a\x80\x81", b"..", "DnKaJeoKOs"),
+        (b"\x80", b"H.", "jPpg5.obl6"),
+        (b"\xa4\xa2", b".H", "PmFHUdR0vg"),
+        (b"z\xff", b".H", "LZeXp3faAI"),
+        (b"ab\xff", b"b.", "1dJ9IQrL1E"),
+        (b"/", b"H.", "DGAVybFN0A"),
+        (b"//", b"/H", "Cq22qYPmyU"),
+        (b"..", b".H", "dNQixK/.Y."),
+        (b"a..", b"..", "fF7j1eDfTY"),
+        (b"A/.", b"/.", "Drp1VWUsXo"),
+        (b"0123", b"12", "DlyW/s8ibQ"),
+        (b"!!!!!!!!", b"..", "01BuaIZRLs"),
+        (b"~~~~~~~~", b"..", "mSHEZSRvLs"),
+        (b"AAAAAAAA", b"AA", "DLUg7SsaxM"),
+        (b"zzzzzzzz", b"zz", "4PRDdoNSjw"),
+        (b"\x01\x02\x03", b"..", "hj33lHtKW6"),
+        (b"tripcode", b"ri", "3GqYIJ3Obs"),
+        (b"#!/bin/s", b"./", "opAs2QK/cs"),
+        (b"\\\\\\\\", b"bb", "920yvwVcg6"),
+    ];
+
+    fn padded(passwd: &[u8]) -> [u8; 8] {
+        let mut padded = [0; 8];
+        padded[..passwd.len()].copy_from_slice(passwd);
+        padded
+    }
+
+    #[test]
+    fn known_answers() {
+        for &(passwd, salt, tripcode) in KNOWN_ANSWERS {
+            assert_eq!(&tripcode_salt(passwd), salt, "salt of {:?}", passwd);
+            assert_eq!(trip(padded(passwd)), tripcode, "tripcode of {:?}", passwd);
+        }
+    }
+
+    #[test]
+    fn self_test_passes() {
+        self_test().unwrap();
+    }
+
+    #[test]
+    fn compat() {
+        let profile = |compat| Profile { map_salt: false, compat };
+        let with = |passwd: &[u8], compat| trip_with(padded(passwd), profile(compat));
+        let salted = |passwd: &[u8], salt: &[u8]| {
+            let hash = crypt(passwd, salt).unwrap();
+            Some(Tripcode::new(&hash.as_bytes()[3..]).unwrap())
+        };
+
+        // With 2channel's mapping every salt is valid, so every implementation agrees.
+        for &(passwd, _, tripcode) in KNOWN_ANSWERS {
+            for &compat in &[
+                Compat::FreeSec,
+                Compat::Strict,
+                Compat::Ufc,
+                Compat::UfcUnsigned,
+                Compat::Bsd,
+            ] {
+                let profile = Profile { map_salt: true, compat };
+                let found = trip_with(padded(passwd), profile);
+                assert_eq!(found.map(|t| t.to_string()), Some(tripcode.to_string()));
+            }
+        }
+
+        // Otherwise each invalid character hashes like the valid one it is decoded as.
+        assert_eq!(with(b"x;?", Compat::FreeSec), salted(b"x;?", b"BF"));
+        assert_eq!(with(b"x:A", Compat::FreeSec), None);
+        assert_eq!(with(b"x:A", Compat::Ufc), salted(b"x:A", b"AA"));
+        assert_eq!(with(b"x\xe3\x81", Compat::Ufc), salted(b"x\xe3\x81", b"pH"));
+        assert_eq!(with(b"x\xe3\x81", Compat::UfcUnsigned), salted(b"x\xe3\x81", b"c4"));
+        assert_eq!(with(b"x{ ", Compat::Ufc), salted(b"x{ ", b".m"));
+        assert_eq!(with(b"x{ ", Compat::UfcUnsigned), salted(b"x{ ", b".m"));
+        assert_eq!(with(b"x\xe3;", Compat::Bsd), salted(b"x\xe3;", b".."));
+        assert_eq!(with(b"x;A", Compat::Strict), None);
+        assert_eq!(with(b"xAb", Compat::Strict), salted(b"xAb", b"Ab"));
+
+        assert_eq!("ufc-unsigned".parse(), Ok(Compat::UfcUnsigned));
+        let err = "glibc".parse::<Compat>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown crypt implementation glibc");
+    }
+
+    #[test]
+    fn crypt_known_answers() {
+        assert_eq!(crypt(b"test", b"aa").unwrap(), "aaqPiZY5xR5l.");
+        assert_eq!(crypt(b"Hello world!", b"saltstring").unwrap(), "saszt8mUri4AI");
+        assert_eq!(crypt(b"", b"ab").unwrap(), "abmF1QH4PEr.E");
+        assert_eq!(crypt(b"longerthan8chars", b"Zz").unwrap(), "ZzB6eMPDY2lRE");
+        assert_eq!(crypt(b"passwor\0d", b"./").unwrap(), "./1POZirKozTY");
+        assert!(crypt(b"test", b"a").is_err());
+        assert!(crypt(b"test", b"a\xff").is_err());
+        assert!(crypt(b"x", b"a:").is_err());
+        assert!(crypt(b"x", b" a").is_err());
+    }
+
+    #[test]
+    fn crypt_extended_known_answers() {
+        assert_eq!(crypt(b"U*U*U*U*", b"_J9..CCCC").unwrap(), "_J9..CCCCXBrJUJV154M");
+        assert_eq!(crypt(b"U*U***U", b"_J9..CCCC").unwrap(), "_J9..CCCCXUhOBTXzaiE");
+        assert_eq!(crypt(b"", b"_J9..SDiz").unwrap(), "_J9..SDizUsxsXqYns0c");
+        assert_eq!(
+            crypt(b"0123456789abcdefghij", b"_K1..crsm").unwrap(),
+            "_K1..crsmN8zn5J/gUnw"
+        );
+        assert_eq!(
+            crypt(b"multiple words here!\0ignored", b"_zz..1234").unwrap(),
+            "_zz..1234EZfKoxM9kXg"
+        );
+        assert_eq!(
+            crypt(b"\xff\xfe\xa0 high bits ok", b"_.../abcd").unwrap(),
+            "_.../abcdRmzUXCVJgbQ"
+        );
+        assert!(crypt(b"short", b"_/...").is_err());
+        assert!(crypt(b"zero", b"_....abcd").is_err());
+        assert!(crypt(b"x", b"_!!!!abcd").is_err());
+        assert!(crypt(b"x", b"_J9..CC!C").is_err());
+        assert!(crypt(b"x", b"_J9..CC\x80C").is_err());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "libcrypt-tests"))]
+    #[test]
+    fn matches_libc_crypt() {
+        use libc::c_char;
+        use rng::{Counter, Generator};
+        use std::ffi::CStr;
+
+        #[link(name = "crypt")]
+        extern "C" {
+            #[link_name = "crypt"]
+            fn libc_crypt(key: *const c_char, salt: *const c_char) -> *const c_char;
+        }
+
+        // The hash libcrypt gives, or None if it refuses the setting, as builds without the
+        // weaker hashes do.
+        fn system_crypt(key: &[u8], setting: &[u8]) -> Option<&'static [u8]> {
+            let hash = unsafe { libc_crypt(key.as_ptr() as _, setting.as_ptr() as _) };
+
+            if hash.is_null() {
+                return None;
+            }
+
+            Some(unsafe { CStr::from_ptr(hash) }.to_bytes()).filter(|hash| !hash.starts_with(b"*"))
+        }
+
+        let mut rng = Counter::new(0x7472_6970);
+
+        for _ in 0..10000 {
+            let word = rng.next_u64();
+            let len = 1 + (word >> 61) as usize;
+            let mut passwd = [0u8; 9];
+
+            // Any bytes but NUL, including ones with the high bit set.
+            for (i, c) in passwd[..len].iter_mut().enumerate() {
+                *c = ((word >> (i * 7)) as u8).max(1);
+            }
+
+            let salt = tripcode_salt(&passwd);
+            let salt = [salt[0], salt[1], 0];
+            let hash = system_crypt(&passwd, &salt).expect("libcrypt refused a DES salt");
+
+            assert_eq!(
+                trip(padded(&passwd[..len])).as_bytes(),
+                &hash[3..],
+                "tripcode of {:?}",
+                &passwd[..len]
+            );
+            assert_eq!(super::crypt(&passwd, &salt).unwrap().as_bytes(), hash);
+        }
+
+        // Extended settings with keys of up to 24 bytes and small iteration counts.
+        for _ in 0..500 {
+            let word = rng.next_u64();
+            let len = (word % 25) as usize;
+            let mut key = [0u8; 25];
+
+            for c in &mut key[..len] {
+                *c = (rng.next_u64() as u8).max(1);
+            }
+
+            let setting = [
+                b'_',
+                ASCII64[(word >> 8 & 0x3f) as usize].max(b'/'),
+                b'.',
+                b'.',
+                b'.',
+                ASCII64[(word >> 16 & 0x3f) as usize],
+                ASCII64[(word >> 24 & 0x3f) as usize],
+                ASCII64[(word >> 32 & 0x3f) as usize],
+                ASCII64[(word >> 40 & 0x3f) as usize],
+                0,
+            ];
+
+            if let Some(hash) = system_crypt(&key, &setting) {
+                assert_eq!(super::crypt(&key, &setting).unwrap().as_bytes(), hash);
+            }
+        }
+    }
+}
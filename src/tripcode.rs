@@ -0,0 +1,124 @@
+use core::fmt;
+use core::str::{self, FromStr};
+
+/// A tripcode: 10 characters for a password hashed with crypt(3), or 12 for one hashed with
+/// SHA-1, over `./0-9A-Za-z`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tripcode {
+    chars: [u8; 12],
+    len: u8,
+}
+
+/// The error returned when a string is not a tripcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseTripcodeError;
+
+impl fmt::Display for ParseTripcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not a tripcode: expected 10 or 12 characters of ./0-9A-Za-z")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ParseTripcodeError {}
+
+fn is_tripcode_char(c: u8) -> bool {
+    c == b'.' || c == b'/' || c.is_ascii_alphanumeric()
+}
+
+impl Tripcode {
+    pub fn new(chars: &[u8]) -> Result<Tripcode, ParseTripcodeError> {
+        let len_ok = chars.len() == 10 || chars.len() == 12;
+
+        if !len_ok || !chars.iter().all(|&c| is_tripcode_char(c)) {
+            return Err(ParseTripcodeError);
+        }
+
+        Ok(Tripcode::new_unchecked(chars))
+    }
+
+    /// A tripcode from characters known to be valid, such as those of a crypt(3) hash.
+    pub(crate) fn new_unchecked(chars: &[u8]) -> Tripcode {
+        let mut tripcode = Tripcode { chars: [0; 12], len: chars.len() as u8 };
+        tripcode.chars[..chars.len()].copy_from_slice(chars);
+        tripcode
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.chars[..self.len as usize]
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ASCII characters are accepted.
+        unsafe { str::from_utf8_unchecked(self.as_bytes()) }
+    }
+}
+
+impl FromStr for Tripcode {
+    type Err = ParseTripcodeError;
+
+    /// Parses a tripcode, which may be preceded by `!` or `!!`.
+    fn from_str(s: &str) -> Result<Tripcode, ParseTripcodeError> {
+        let s = s.strip_prefix('!').unwrap_or(s);
+        let s = s.strip_prefix('!').unwrap_or(s);
+        Tripcode::new(s.as_bytes())
+    }
+}
+
+/// Writes the tripcode alone, or with the alternate flag (`{:#}`) preceded by `!` if it is
+/// 10 characters long and `!!` if it is 12.
+impl fmt::Display for Tripcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str(if self.len == 10 { "!" } else { "!!" })?;
+        }
+
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Tripcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl AsRef<str> for Tripcode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Tripcode {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Tripcode {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let tripcode: Tripcode = "vctoKCJ4Fk".parse().unwrap();
+        assert_eq!(tripcode, "vctoKCJ4Fk");
+        assert_eq!("!vctoKCJ4Fk".parse(), Ok(tripcode));
+        assert_eq!(format!("{}", tripcode), "vctoKCJ4Fk");
+        assert_eq!(format!("{:#}", tripcode), "!vctoKCJ4Fk");
+
+        let secure: Tripcode = "!!Ab.0/9zZ1234".parse().unwrap();
+        assert_eq!(secure.as_str(), "Ab.0/9zZ1234");
+        assert_eq!(format!("{:#}", secure), "!!Ab.0/9zZ1234");
+
+        assert_eq!("vctoKCJ4F".parse::<Tripcode>(), Err(ParseTripcodeError));
+        assert_eq!("vctoKCJ4F+".parse::<Tripcode>(), Err(ParseTripcodeError));
+        assert_eq!("!!!vctoKCJ4Fk".parse::<Tripcode>(), Err(ParseTripcodeError));
+    }
+}
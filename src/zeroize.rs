@@ -0,0 +1,124 @@
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{self, Ordering};
+#[cfg(feature = "std")]
+use std::mem;
+
+/// A value which can be overwritten with zeros in a way the compiler won't optimise away, even
+/// though the value is never read again.
+pub trait Zeroize {
+    fn zeroize(&mut self);
+}
+
+macro_rules! zeroize_int {
+    ($($t:ty)*) => ($(
+        impl Zeroize for $t {
+            fn zeroize(&mut self) {
+                unsafe { ptr::write_volatile(self, 0) };
+                atomic::compiler_fence(Ordering::SeqCst);
+            }
+        }
+    )*)
+}
+
+zeroize_int!(u8 u32 u64);
+
+impl<T: Zeroize> Zeroize for [T] {
+    fn zeroize(&mut self) {
+        for x in self {
+            x.zeroize();
+        }
+    }
+}
+
+impl<T: Zeroize, const N: usize> Zeroize for [T; N] {
+    fn zeroize(&mut self) {
+        self[..].zeroize();
+    }
+}
+
+/// Empties the vector, zeroing its spare capacity as well as its contents.
+#[cfg(feature = "std")]
+impl<T: Zeroize> Zeroize for Vec<T> {
+    fn zeroize(&mut self) {
+        self[..].zeroize();
+        self.clear();
+
+        let bytes = self.capacity() * mem::size_of::<T>();
+        let base = self.as_mut_ptr() as *mut u8;
+
+        for i in 0..bytes {
+            unsafe { ptr::write_volatile(base.add(i), 0) };
+        }
+
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Empties the string, zeroing its spare capacity as well as its contents.
+#[cfg(feature = "std")]
+impl Zeroize for String {
+    fn zeroize(&mut self) {
+        unsafe { self.as_mut_vec() }.zeroize();
+    }
+}
+
+/// A value which is zeroed when dropped.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Zeroizing<T: Zeroize>(T);
+
+impl<T: Zeroize> Zeroizing<T> {
+    pub fn new(value: T) -> Zeroizing<T> {
+        Zeroizing(value)
+    }
+}
+
+impl<T: Zeroize> Deref for Zeroizing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeroes() {
+        let mut key = [0x55u32; 4];
+        key.zeroize();
+        assert_eq!(key, [0; 4]);
+
+        let mut passwd = Vec::with_capacity(16);
+        passwd.extend_from_slice(b"foofoofo");
+        passwd.truncate(3);
+        passwd.zeroize();
+        assert!(passwd.is_empty());
+
+        // The truncated bytes in the spare capacity are zeroed too.
+        unsafe { passwd.set_len(8) };
+        assert_eq!(&passwd[..], &[0; 8]);
+
+        let mut passwd = String::with_capacity(16);
+        passwd.push_str("foofoofo");
+        passwd.zeroize();
+        assert!(passwd.is_empty());
+        let passwd = unsafe { passwd.as_mut_vec() };
+        unsafe { passwd.set_len(8) };
+        assert_eq!(&passwd[..], &[0; 8]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Helpers for the tests which build the libraries into programs in other languages and run
+//! them. Each test crate includes this file with `#[path]`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The directory cargo builds the libraries in, `target/<profile>`. The test binary itself is in
+/// its `deps` directory.
+pub fn lib_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+/// The program named by the environment variable `var`, or `default` if it is unset.
+pub fn program(var: &str, default: &str) -> String {
+    env::var(var).unwrap_or_else(|_| default.to_string())
+}
+
+/// Runs `command`, called `name` in failure messages, and checks that it succeeds.
+pub fn run(name: &str, command: &mut Command) {
+    let status = command.status().unwrap_or_else(|e| panic!("{}: {}", name, e));
+    assert!(status.success(), "{} failed", name);
+}
@@ -0,0 +1,44 @@
+#![cfg(unix)]
+
+extern crate libc;
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn signal_stops_idle_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_trip"))
+        .args(["--stdin", "aa"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // The producer stays open without writing anything, so the workers block reading it.
+    let _stdin = child.stdin.take();
+    thread::sleep(Duration::from_millis(500));
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+
+    let start = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+
+        if start.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("trip did not exit after SIGINT");
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let mut stderr = String::new();
+    child.stderr.take().unwrap().read_to_string(&mut stderr).unwrap();
+    assert!(status.success(), "{}", stderr);
+    assert!(stderr.contains("Stopped because a signal was received"), "{}", stderr);
+}
@@ -0,0 +1,111 @@
+//! WebAssembly exports for the tripcode function, wrapped for JavaScript by `trip.js`.
+//!
+//! The module allocates nothing: the caller writes up to eight bytes of password into the buffer
+//! at `trip_password_buffer()`, calls `trip_compute` with their length and reads the tripcode from
+//! the ten bytes at `trip_tripcode_buffer()`. Return codes and compat numbers are those of the C
+//! API.
+
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+#[cfg(not(target_arch = "wasm32"))]
+extern crate core;
+extern crate trip;
+
+use core::cell::UnsafeCell;
+use trip::trip::{trip_with, Compat, Profile};
+
+const TRIP_OK: i32 = 0;
+const TRIP_ERR_PROFILE: i32 = -2;
+const TRIP_ERR_SALT: i32 = -3;
+
+struct Buffers {
+    password: UnsafeCell<[u8; 8]>,
+    tripcode: UnsafeCell<[u8; 10]>,
+}
+
+// WebAssembly instances run on a single thread.
+unsafe impl Sync for Buffers {}
+
+static BUFFERS: Buffers = Buffers {
+    password: UnsafeCell::new([0; 8]),
+    tripcode: UnsafeCell::new([0; 10]),
+};
+
+#[no_mangle]
+pub extern "C" fn trip_password_buffer() -> *mut u8 {
+    BUFFERS.password.get() as *mut u8
+}
+
+#[no_mangle]
+pub extern "C" fn trip_tripcode_buffer() -> *const u8 {
+    BUFFERS.tripcode.get() as *const u8
+}
+
+/// Computes the tripcode of the first `len` bytes of the password buffer, at most eight, and
+/// clears the buffer.
+#[no_mangle]
+pub extern "C" fn trip_compute(len: usize, map_salt: u32, compat: u32) -> i32 {
+    let compat = match compat {
+        0 => Compat::FreeSec,
+        1 => Compat::Strict,
+        2 => Compat::Ufc,
+        3 => Compat::UfcUnsigned,
+        4 => Compat::Bsd,
+        _ => return TRIP_ERR_PROFILE,
+    };
+
+    let profile = Profile { map_salt: map_salt != 0, compat };
+    let password = unsafe { &mut *BUFFERS.password.get() };
+
+    for c in &mut password[len.min(8)..] {
+        *c = 0;
+    }
+
+    // trip_with zeroes its copy of the password.
+    let tripcode = trip_with(*password, profile);
+    *password = [0; 8];
+
+    match tripcode {
+        Some(tripcode) => {
+            unsafe { (*BUFFERS.tripcode.get()).copy_from_slice(tripcode.as_bytes()) };
+            TRIP_OK
+        }
+        None => TRIP_ERR_SALT,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    core::arch::wasm32::unreachable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::slice;
+
+    fn compute(password: &[u8], map_salt: u32, compat: u32) -> Result<String, i32> {
+        let buffer = unsafe { slice::from_raw_parts_mut(trip_password_buffer(), 8) };
+        buffer[..password.len()].copy_from_slice(password);
+
+        match trip_compute(password.len(), map_salt, compat) {
+            TRIP_OK => {
+                let tripcode = unsafe { slice::from_raw_parts(trip_tripcode_buffer(), 10) };
+                Ok(String::from_utf8(tripcode.to_vec()).unwrap())
+            }
+            e => Err(e),
+        }
+    }
+
+    #[test]
+    fn exports() {
+        assert_eq!(compute(b"foofoofo", 1, 0), Ok("vctoKCJ4Fk".to_string()));
+        assert_eq!(compute(b"a", 1, 0), Ok("ZnBI2EKkq.".to_string()));
+        assert_eq!(compute(b"a:;", 1, 0), Ok("6FsyncvrHk".to_string()));
+        assert_eq!(compute(b"a:;", 0, 0), Err(TRIP_ERR_SALT));
+        assert_eq!(compute(b"a:;", 0, 5), Err(TRIP_ERR_PROFILE));
+        assert_eq!(compute(b"tripcode", 1, 0), Ok("3GqYIJ3Obs".to_string()));
+        assert_eq!(unsafe { slice::from_raw_parts(trip_password_buffer(), 8) }, &[0; 8]);
+    }
+}
@@ -0,0 +1,19 @@
+// Checks trip.js against the module given as the first argument.
+
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+import { load } from "../trip.js";
+
+const trip = await load(readFile(process.argv[2]));
+
+assert.equal(trip("foofoofo"), "vctoKCJ4Fk");
+assert.equal(trip("tripcodes"), "3GqYIJ3Obs");
+assert.equal(trip("a"), "ZnBI2EKkq.");
+assert.equal(trip(""), "8NBuQ4l6uQ");
+assert.equal(trip("あ"), "VTkshKs7a.");
+assert.equal(trip(new Uint8Array([0x82, 0xa0, 0x82, 0xa2])), "8udsPUAZG2");
+
+assert.equal(trip("a:;"), "6FsyncvrHk");
+assert.equal(trip("a:;", { mapSalt: true, compat: "strict" }), "6FsyncvrHk");
+assert.throws(() => trip("a:;", { mapSalt: false }), /salt refused/);
+assert.throws(() => trip("a:;", { compat: "glibc" }), /unknown crypt implementation/);
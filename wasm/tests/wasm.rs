@@ -0,0 +1,50 @@
+//! Builds the module for `wasm32-unknown-unknown` and runs `trip.test.mjs` against it with Node.js.
+//!
+//! The target isn't installed by default, so the test only runs when asked for, with
+//! `cargo test -p trip-wasm -- --ignored`.
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+#[test]
+#[ignore = "needs the wasm32-unknown-unknown target and Node.js"]
+fn node_tests() {
+    let rustc = common::program("RUSTC", "rustc");
+    let libdir = Command::new(&rustc)
+        .args(["--print", "target-libdir", "--target", TARGET])
+        .output()
+        .unwrap();
+
+    assert!(
+        Path::new(String::from_utf8(libdir.stdout).unwrap().trim()).exists(),
+        "the {} target is not installed",
+        TARGET
+    );
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // The module is built in a target directory of its own, as cargo holds a lock on this one
+    // while testing.
+    let target_dir = common::lib_dir().parent().unwrap().join("wasm");
+    let cargo = common::program("CARGO", "cargo");
+
+    common::run(
+        &format!("building for {}", TARGET),
+        Command::new(&cargo)
+            .args(["build", "--release", "-p", "trip-wasm", "--target", TARGET, "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(manifest_dir),
+    );
+
+    let module = target_dir.join(TARGET).join("release/trip_wasm.wasm");
+    let node = common::program("NODE", "node");
+
+    common::run(
+        &node,
+        Command::new(&node).arg(manifest_dir.join("tests/trip.test.mjs")).arg(&module),
+    );
+}
@@ -0,0 +1,54 @@
+// Loads the WebAssembly build of trip, returning a function which takes a password and returns
+// its tripcode:
+//
+//     import { load } from "./trip.js";
+//     const trip = await load(fetch("trip_wasm.wasm"));
+//     trip("foofoofo"); // "vctoKCJ4Fk"
+//
+// Strings are encoded as UTF-8. Boards which use another encoding, such as Shift_JIS, can pass
+// the encoded password as a Uint8Array instead. Only the first eight bytes are used.
+//
+// The optional second argument is the board's profile, { mapSalt, compat }, 2channel's rules
+// ({ mapSalt: true, compat: "freesec" }) by default. An Error is thrown if the profile's crypt(3)
+// refuses the password's salt.
+
+const COMPAT = ["freesec", "strict", "ufc", "ufc-unsigned", "bsd"];
+
+const ERRORS = new Map([
+    [-2, "unknown crypt implementation in profile"],
+    [-3, "salt refused by the profile's crypt implementation"],
+]);
+
+// source is the module's bytes, a Response or a promise of either.
+export async function load(source) {
+    source = await source;
+
+    const { instance } = typeof Response !== "undefined" && source instanceof Response
+        ? await WebAssembly.instantiateStreaming(source)
+        : await WebAssembly.instantiate(source);
+
+    const exports = instance.exports;
+    const encoder = new TextEncoder();
+    const decoder = new TextDecoder();
+
+    return function trip(password, { mapSalt = true, compat = "freesec" } = {}) {
+        const bytes = typeof password === "string" ? encoder.encode(password) : password;
+        const compatIndex = COMPAT.indexOf(compat);
+
+        if (compatIndex < 0) {
+            throw new Error(`unknown crypt implementation ${compat}`);
+        }
+
+        const len = Math.min(bytes.length, 8);
+        const buffer = exports.trip_password_buffer() >>> 0;
+        new Uint8Array(exports.memory.buffer, buffer, 8).set(bytes.subarray(0, len));
+        const code = exports.trip_compute(len, mapSalt ? 1 : 0, compatIndex);
+
+        if (code !== 0) {
+            throw new Error(ERRORS.get(code) ?? `error ${code}`);
+        }
+
+        const tripcode = exports.trip_tripcode_buffer() >>> 0;
+        return decoder.decode(new Uint8Array(exports.memory.buffer, tripcode, 10));
+    };
+}